@@ -0,0 +1,224 @@
+use sun_transit::*;
+use moon_phase::*;
+use context::GeodateContext;
+use error::Error;
+use geodate::{Epoch, Calendar, SolarTime, YearStart};
+
+use alloc::vec::Vec;
+#[cfg(not(feature = "std"))]
+use num_traits::Float;
+
+// Mean interval between two occurrences of the same solstice or equinox
+const TROPICAL_YEAR: f64 = 365.2422 * 86400.0;
+
+// Timestamps of the beginning of the epochs
+const GREGORIAN_ZERO: i64 = -2208988800; // 1900-01-01
+const UNIX_ZERO: i64 = 0; // 1970-01-01
+
+pub fn get_epoch_zero(epoch: Epoch) -> i64 {
+    match epoch {
+        Epoch::Unix      => UNIX_ZERO,
+        Epoch::Gregorian => GREGORIAN_ZERO,
+        Epoch::Custom(t) => t
+    }
+}
+
+// Get the midnight at the beginning of the day of the given timestamp
+pub fn get_day_start(timestamp: i64, longitude: f64, solar_time: SolarTime) -> i64 {
+    if solar_time == SolarTime::Mean {
+        // The mean solar time is ahead of UTC by 4 minutes per degree east
+        let offset = (longitude * 240.0).round() as i64;
+        return (timestamp + offset).div_euclid(86400) * 86400 - offset;
+    }
+
+    let midnight = get_midnight(timestamp, longitude);
+    if midnight > timestamp {
+        midnight - 86400
+    } else if midnight <= timestamp - 86400 {
+        midnight + 86400
+    } else {
+        midnight
+    }
+}
+
+// Count the days between two midnights
+pub fn count_days(from: i64, to: i64) -> i64 {
+    ((to - from) as f64 / 86400.0).round() as i64
+}
+
+// Get the fraction of a year between the beginning of an epoch and the
+// solstice or the equinox starting its first year
+fn get_year_start_offset(year_start: YearStart) -> f64 {
+    match year_start {
+        YearStart::DecemberSolstice => 0.0,
+        YearStart::MarchEquinox     => 0.25,
+        YearStart::JuneSolstice     => 0.5
+    }
+}
+
+// Get the solstice or the equinox starting the year 0 of the epoch, which
+// is the first one after half a year before the beginning of the epoch
+// shifted by the fraction of a year of the year start
+fn try_get_first_year_start(context: &mut GeodateContext) -> Result<i64, Error> {
+    let offset = get_year_start_offset(context.year_start);
+    let t = get_epoch_zero(context.epoch).checked_add(((offset - 0.5) * 365.25 * 86400.0) as i64);
+    context.try_get_next_year_start(t.ok_or(Error::OutOfRange)? - 1)
+}
+
+// Get the number of the year starting with the day of a solstice or an
+// equinox, counting the solstices or the equinoxes from the first year of
+// the epoch
+pub fn try_get_year_number(context: &mut GeodateContext, solstice: i64) -> Result<i64, Error> {
+    let first = try_get_first_year_start(context)?;
+    Ok(((solstice - first) as f64 / TROPICAL_YEAR).round() as i64)
+}
+
+// Get the day of the solstice or the equinox starting a year
+pub fn try_get_year_solstice(context: &mut GeodateContext, year: i64) -> Result<i64, Error> {
+    let (lon, st) = (context.longitude, context.solar_time);
+    let first = try_get_first_year_start(context)?;
+    let t = first.checked_add((year as f64 * TROPICAL_YEAR) as i64).ok_or(Error::OutOfRange)?;
+    let mut solstice = context.try_get_previous_year_start(t.checked_add(182 * 86400).ok_or(Error::OutOfRange)?)?;
+
+    // Walk the solstices or the equinoxes to the one of the year
+    loop {
+        let n = try_get_year_number(context, get_day_start(solstice, lon, st))?;
+        if n < year {
+            solstice = context.try_get_next_year_start(solstice)?;
+        } else if n > year {
+            solstice = context.try_get_previous_year_start(solstice)?;
+        } else {
+            return Ok(get_day_start(solstice, lon, st));
+        }
+    }
+}
+
+// Get the lunation number of the first new moon of a lunisolar year, which
+// is the first new moon falling on the day of the solstice or the equinox
+// starting the year or after
+pub fn try_get_first_lunation(context: &mut GeodateContext, solstice: i64) -> Result<i64, Error> {
+    let (lon, st) = (context.longitude, context.solar_time);
+    let mut k = get_lunation_number(solstice) as i64 + 1;
+    while count_days(solstice, get_day_start(context.try_get_new_moon(k)?, lon, st)) < 0 {
+        k += 1;
+    }
+
+    // The new moons before the first solstice of the supported range are
+    // out of range, but cannot be the first of the year anyway
+    while let Ok(new_moon) = context.try_get_new_moon(k - 1) {
+        if count_days(solstice, get_day_start(new_moon, lon, st)) < 0 {
+            break;
+        }
+        k -= 1;
+    }
+    Ok(k)
+}
+
+// Get the lunation number and the day of the last new moon falling on the
+// day of a midnight or before
+pub fn try_get_last_new_moon(context: &mut GeodateContext, midnight: i64) -> Result<(i64, i64), Error> {
+    let (lon, st) = (context.longitude, context.solar_time);
+    let mut n = get_lunation_number(midnight) as i64 + 2;
+    let mut new_moon = get_day_start(context.try_get_new_moon(n)?, lon, st);
+    while count_days(new_moon, midnight) < 0 {
+        n -= 1;
+        new_moon = get_day_start(context.try_get_new_moon(n)?, lon, st);
+    }
+    Ok((n, new_moon))
+}
+
+// Get the days starting the months of the year starting on the day of a
+// solstice or an equinox, followed by the day starting the next year
+pub fn try_get_month_starts(context: &mut GeodateContext, solstice: i64) -> Result<Vec<i64>, Error> {
+    let (lon, st) = (context.longitude, context.solar_time);
+    let next_solstice = context.try_get_next_year_start(solstice + 86400)?;
+    let next_solstice = get_day_start(next_solstice, lon, st);
+
+    let mut months = Vec::new();
+    match context.calendar {
+        Calendar::Lunisolar => {
+            let first = try_get_first_lunation(context, solstice)?;
+            let last = try_get_first_lunation(context, next_solstice)?;
+            for n in first..(last + 1) {
+                months.push(get_day_start(context.try_get_new_moon(n)?, lon, st));
+            }
+        },
+        Calendar::Solar => {
+            let march = get_day_start(context.try_get_next_march_equinox(solstice)?, lon, st);
+            let june = get_day_start(context.try_get_next_june_solstice(solstice)?, lon, st);
+            let september = get_day_start(context.try_get_next_september_equinox(solstice)?, lon, st);
+            let december = get_day_start(context.try_get_next_december_solstice(solstice)?, lon, st);
+            months.push(solstice);
+            months.extend(match context.year_start {
+                YearStart::DecemberSolstice => [march, june, september],
+                YearStart::MarchEquinox     => [june, september, december],
+                YearStart::JuneSolstice     => [september, december, march]
+            }.iter());
+            months.push(next_solstice);
+        }
+    }
+    Ok(months)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use utils::*;
+
+    #[test]
+    fn try_get_month_starts_test() {
+        let mut context = GeodateContext::new(0.0, Epoch::Unix, Calendar::Lunisolar);
+        let solstice = try_get_year_solstice(&mut context, 0).unwrap();
+        assert_eq!(0, try_get_year_number(&mut context, solstice).unwrap());
+        let months = try_get_month_starts(&mut context, solstice).unwrap();
+        assert_eq!(13, months.len());
+        assert_eq!(parse_time("1970-01-07T00:06:14+00:00"), months[0]);
+        assert_eq!(parse_time("1970-12-28T00:01:28+00:00"), months[12]);
+
+        let mut context = GeodateContext::new(0.0, Epoch::Unix, Calendar::Solar);
+        let solstice = try_get_year_solstice(&mut context, 44).unwrap();
+        assert_eq!(44, try_get_year_number(&mut context, solstice).unwrap());
+        let months = try_get_month_starts(&mut context, solstice).unwrap();
+        assert_eq!(5, months.len());
+    }
+
+    #[test]
+    fn try_get_month_starts_with_year_start_test() {
+        let year_starts = [
+            (YearStart::DecemberSolstice, "1969-12-22T00:00:00+00:00"),
+            (YearStart::MarchEquinox, "1970-03-21T00:00:00+00:00"),
+            (YearStart::JuneSolstice, "1970-06-21T00:00:00+00:00")
+        ];
+        for &(year_start, t) in year_starts.iter() {
+            let context = GeodateContext::new(0.0, Epoch::Unix, Calendar::Solar);
+            let mut context = context.with_year_start(year_start);
+            let solstice = try_get_year_solstice(&mut context, 0).unwrap();
+            assert_eq!(0, count_days(parse_time(t), solstice));
+            assert_eq!(0, try_get_year_number(&mut context, solstice).unwrap());
+
+            let months = try_get_month_starts(&mut context, solstice).unwrap();
+            assert_eq!(5, months.len());
+            for i in 1..months.len() {
+                let days = count_days(months[i - 1], months[i]);
+                assert!((88..=95).contains(&days));
+            }
+            assert_eq!(Ok(months[4]), try_get_year_solstice(&mut context, 1));
+        }
+    }
+
+    #[test]
+    fn try_get_first_lunation_test() {
+        // A new moon on the day of the solstice starts the year whether it
+        // happens before or after the solstice
+        let times = [
+            (0.0, "1900-12-22T00:01:08+00:00", "1900-12-22T06:41:46+00:00"),
+            (150.0, "1938-12-21T18:06:48+00:00", "1938-12-22T12:13:31+00:00")
+        ];
+        for &(lon, new_moon, solstice) in times.iter() {
+            let mut context = GeodateContext::new(lon, Epoch::Gregorian, Calendar::Lunisolar);
+            let solstice = get_day_start(parse_time(solstice), lon, SolarTime::Apparent);
+            let k = try_get_first_lunation(&mut context, solstice).unwrap();
+            assert_approx_eq!(parse_time(new_moon) as f64, context.try_get_new_moon(k).unwrap() as f64, 60.0);
+        }
+    }
+}
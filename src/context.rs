@@ -0,0 +1,212 @@
+use earth_orbit::{Event, get_previous_time_of, get_next_time_of};
+use moon_phase::{self, MoonPhase};
+use delta_time::DeltaTimeTable;
+use error::Error;
+use geodate::{Epoch, Calendar, SolarTime, YearStart};
+
+use alloc::collections::BTreeMap;
+use alloc::collections::BTreeSet;
+
+// Shortest interval between two occurrences of the same solstice or equinox
+const MIN_YEAR_INTERVAL: i64 = 365 * 86400;
+
+/// A calendar configuration for an observer
+///
+/// The context memoizes the times of the new moons, solstices, and equinoxes
+/// computed during a conversion, so that the following conversions of nearby
+/// timestamps only need to look them up.
+#[derive(Clone, Debug)]
+pub struct GeodateContext {
+    pub longitude: f64,
+    pub epoch: Epoch,
+    pub calendar: Calendar,
+    pub year_start: YearStart,
+    pub solar_time: SolarTime,
+    pub delta_time_table: Option<DeltaTimeTable>,
+    new_moons: BTreeMap<i64, i64>,
+    march_equinoxes: BTreeSet<i64>,
+    june_solstices: BTreeSet<i64>,
+    september_equinoxes: BTreeSet<i64>,
+    december_solstices: BTreeSet<i64>
+}
+
+impl GeodateContext {
+    pub fn new(longitude: f64, epoch: Epoch, calendar: Calendar) -> GeodateContext {
+        GeodateContext {
+            longitude,
+            epoch,
+            calendar,
+            year_start: YearStart::default(),
+            solar_time: SolarTime::default(),
+            delta_time_table: None,
+            new_moons: BTreeMap::new(),
+            march_equinoxes: BTreeSet::new(),
+            june_solstices: BTreeSet::new(),
+            september_equinoxes: BTreeSet::new(),
+            december_solstices: BTreeSet::new()
+        }
+    }
+
+    /// Set the solstice or the equinox starting the years of the calendar
+    pub fn with_year_start(mut self, year_start: YearStart) -> GeodateContext {
+        self.year_start = year_start;
+        self
+    }
+
+    /// Set the solar time giving the midnights starting the days
+    pub fn with_solar_time(mut self, solar_time: SolarTime) -> GeodateContext {
+        self.solar_time = solar_time;
+        self
+    }
+
+    /// Set a table of ΔT overriding the built-in estimate for the years it
+    /// covers
+    pub fn with_delta_time_table(mut self, table: DeltaTimeTable) -> GeodateContext {
+        self.delta_time_table = Some(table);
+        self
+    }
+
+    pub fn try_get_new_moon(&mut self, lunation_number: i64) -> Result<i64, Error> {
+        if let Some(&t) = self.new_moons.get(&lunation_number) {
+            return Ok(t);
+        }
+        let t = moon_phase::get_time_of(MoonPhase::NewMoon, lunation_number as f64, self.delta_time_table.as_ref())?;
+        self.new_moons.insert(lunation_number, t);
+        Ok(t)
+    }
+
+    pub fn try_get_previous_march_equinox(&mut self, timestamp: i64) -> Result<i64, Error> {
+        get_previous(&mut self.march_equinoxes, timestamp, Event::MarchEquinox, self.delta_time_table.as_ref())
+    }
+
+    pub fn try_get_next_march_equinox(&mut self, timestamp: i64) -> Result<i64, Error> {
+        get_next(&mut self.march_equinoxes, timestamp, Event::MarchEquinox, self.delta_time_table.as_ref())
+    }
+
+    pub fn try_get_previous_june_solstice(&mut self, timestamp: i64) -> Result<i64, Error> {
+        get_previous(&mut self.june_solstices, timestamp, Event::JuneSolstice, self.delta_time_table.as_ref())
+    }
+
+    pub fn try_get_next_june_solstice(&mut self, timestamp: i64) -> Result<i64, Error> {
+        get_next(&mut self.june_solstices, timestamp, Event::JuneSolstice, self.delta_time_table.as_ref())
+    }
+
+    pub fn try_get_previous_september_equinox(&mut self, timestamp: i64) -> Result<i64, Error> {
+        get_previous(&mut self.september_equinoxes, timestamp, Event::SeptemberEquinox, self.delta_time_table.as_ref())
+    }
+
+    pub fn try_get_next_september_equinox(&mut self, timestamp: i64) -> Result<i64, Error> {
+        get_next(&mut self.september_equinoxes, timestamp, Event::SeptemberEquinox, self.delta_time_table.as_ref())
+    }
+
+    pub fn try_get_previous_december_solstice(&mut self, timestamp: i64) -> Result<i64, Error> {
+        get_previous(&mut self.december_solstices, timestamp, Event::DecemberSolstice, self.delta_time_table.as_ref())
+    }
+
+    pub fn try_get_next_december_solstice(&mut self, timestamp: i64) -> Result<i64, Error> {
+        get_next(&mut self.december_solstices, timestamp, Event::DecemberSolstice, self.delta_time_table.as_ref())
+    }
+
+    /// Get the previous solstice or equinox starting a year
+    pub fn try_get_previous_year_start(&mut self, timestamp: i64) -> Result<i64, Error> {
+        match self.year_start {
+            YearStart::DecemberSolstice => self.try_get_previous_december_solstice(timestamp),
+            YearStart::JuneSolstice     => self.try_get_previous_june_solstice(timestamp),
+            YearStart::MarchEquinox     => self.try_get_previous_march_equinox(timestamp)
+        }
+    }
+
+    /// Get the next solstice or equinox starting a year
+    pub fn try_get_next_year_start(&mut self, timestamp: i64) -> Result<i64, Error> {
+        match self.year_start {
+            YearStart::DecemberSolstice => self.try_get_next_december_solstice(timestamp),
+            YearStart::JuneSolstice     => self.try_get_next_june_solstice(timestamp),
+            YearStart::MarchEquinox     => self.try_get_next_march_equinox(timestamp)
+        }
+    }
+}
+
+// A cached event less than a year before the timestamp is necessarily the
+// previous one, because the same event cannot happen twice in a year.
+fn get_previous(cache: &mut BTreeSet<i64>, timestamp: i64, event: Event, table: Option<&DeltaTimeTable>) -> Result<i64, Error> {
+    if let Some(&t) = cache.range(..timestamp).next_back() {
+        if timestamp - t < MIN_YEAR_INTERVAL {
+            return Ok(t);
+        }
+    }
+    let t = get_previous_time_of(event, timestamp, table)?;
+    cache.insert(t);
+    Ok(t)
+}
+
+fn get_next(cache: &mut BTreeSet<i64>, timestamp: i64, event: Event, table: Option<&DeltaTimeTable>) -> Result<i64, Error> {
+    if let Some(&t) = cache.range((timestamp + 1)..).next() {
+        if t - timestamp < MIN_YEAR_INTERVAL {
+            return Ok(t);
+        }
+    }
+    let t = get_next_time_of(event, timestamp, table)?;
+    cache.insert(t);
+    Ok(t)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use delta_time::*;
+    use julian::*;
+    use earth_orbit::*;
+    use moon_phase::*;
+    use utils::*;
+
+    #[test]
+    fn try_get_new_moon_test() {
+        let mut context = GeodateContext::new(0.0, Epoch::Gregorian, Calendar::Lunisolar);
+        assert_eq!(Ok(get_new_moon(-283.0)), context.try_get_new_moon(-283));
+        assert_eq!(Ok(get_new_moon(-283.0)), context.try_get_new_moon(-283));
+        assert_eq!(1, context.new_moons.len());
+    }
+
+    #[test]
+    fn try_get_december_solstice_test() {
+        let mut context = GeodateContext::new(0.0, Epoch::Gregorian, Calendar::Lunisolar);
+        let times = vec![
+            parse_time("2013-06-01T00:00:00+00:00"),
+            parse_time("2013-12-21T12:00:00+00:00"),
+            parse_time("2013-12-22T12:00:00+00:00"),
+            parse_time("2014-06-01T00:00:00+00:00"),
+            parse_time("2012-12-01T00:00:00+00:00"),
+            parse_time("2013-01-01T00:00:00+00:00")
+        ];
+        for &t in &times {
+            assert_eq!(Ok(get_previous_december_solstice(t)), context.try_get_previous_december_solstice(t));
+            assert_eq!(Ok(get_next_december_solstice(t)), context.try_get_next_december_solstice(t));
+        }
+        assert_eq!(4, context.december_solstices.len());
+    }
+
+    #[test]
+    fn try_get_year_start_test() {
+        let t = parse_time("2013-06-01T00:00:00+00:00");
+        let mut context = GeodateContext::new(0.0, Epoch::Gregorian, Calendar::Lunisolar);
+        assert_eq!(Ok(get_previous_december_solstice(t)), context.try_get_previous_year_start(t));
+
+        let mut context = context.with_year_start(YearStart::JuneSolstice);
+        assert_eq!(Ok(get_next_june_solstice(t)), context.try_get_next_year_start(t));
+        assert_eq!(Ok(get_previous_june_solstice(t)), context.try_get_previous_year_start(t));
+    }
+
+    #[test]
+    fn delta_time_table_test() {
+        let table = DeltaTimeTable::new(vec![(2013.0, 1000.0), (2014.0, 1000.0)]);
+        let mut context = GeodateContext::new(0.0, Epoch::Gregorian, Calendar::Lunisolar).with_delta_time_table(table);
+
+        // The events are moved earlier by the difference with the estimate
+        let t = parse_time("2013-06-01T00:00:00+00:00");
+        let solstice = context.try_get_next_december_solstice(t).unwrap();
+        assert_approx_eq!(1000.0 - delta_time(2013.97), (get_next_december_solstice(t) - solstice) as f64, 1.0);
+        let n = get_lunation_number(t) as i64;
+        let new_moon = context.try_get_new_moon(n).unwrap();
+        assert_approx_eq!(1000.0 - delta_time(unix_to_year(new_moon)), (get_new_moon(n as f64) - new_moon) as f64, 1.0);
+    }
+}
@@ -0,0 +1,135 @@
+use math::*;
+use julian::*;
+use observer::Observer;
+
+#[cfg(not(feature = "std"))]
+use num_traits::Float;
+
+// Get the mean sidereal time at Greenwich in degrees for a julian day in
+// universal time
+pub fn get_sidereal_time(jd: f64) -> f64 {
+    // Julian century
+    let t = jde_to_julian_century(jd);
+
+    // (formula 12.4)
+    // (θ0)
+    let th0 = 280.460_618_37
+            + 360.985_647_366_29 * (jd - J2000)
+            + 0.000_387_933 * t.powi(2)
+            - t.powi(3) / 38_710_000.0;
+
+    modulo(th0, 360.0)
+}
+
+// Get the azimuth measured clockwise from the north and the altitude of
+// a body given by its right ascension and declination, seen by an observer
+// at a local sidereal time
+pub fn get_horizontal_coordinates(observer: &Observer, sidereal_time: f64, right_ascension: f64, declination: f64) -> (f64, f64) {
+    let lat = observer.latitude();
+
+    // Local hour angle
+    // (H)
+    let hh = sidereal_time + observer.longitude() - right_ascension;
+
+    // Azimuth measured westward from the south
+    // (formula 13.5)
+    // (A)
+    let a = atan2_deg(sin_deg(hh), cos_deg(hh) * sin_deg(lat) - tan_deg(declination) * cos_deg(lat));
+
+    // Altitude
+    // (formula 13.6)
+    // (h)
+    let h = asin_deg(sin_deg(lat) * sin_deg(declination) + cos_deg(lat) * cos_deg(declination) * cos_deg(hh));
+
+    (modulo(a + 180.0, 360.0), h)
+}
+
+// Get the topocentric right ascension and declination of a body seen by an
+// observer from its geocentric coordinates and its equatorial horizontal
+// parallax, shifted by the position of the observer away from the center of
+// the Earth
+pub fn get_topocentric_coordinates(observer: &Observer, sidereal_time: f64, right_ascension: f64, declination: f64, parallax: f64) -> (f64, f64) {
+    let lat = observer.latitude();
+
+    // Ratio between the polar and the equatorial radius of the Earth, and
+    // elevation of the observer in equatorial radius
+    let b_a = 0.996_647_19;
+    let h = observer.elevation() / 6_378_140.0;
+
+    // Geocentric latitude and distance of the observer
+    // (formula 11.1)
+    let u = atan2_deg(b_a * sin_deg(lat), cos_deg(lat));
+    let rs = b_a * sin_deg(u) + h * sin_deg(lat); // (ρ sin φ')
+    let rc = cos_deg(u) + h * cos_deg(lat);       // (ρ cos φ')
+
+    // Local hour angle
+    // (H)
+    let hh = sidereal_time + observer.longitude() - right_ascension;
+
+    // (formula 40.2 and 40.3)
+    // (Δα, δ')
+    let sp = sin_deg(parallax);
+    let y = cos_deg(declination) - rc * sp * cos_deg(hh);
+    let da = atan2_deg(-rc * sp * sin_deg(hh), y);
+    let d = atan2_deg((sin_deg(declination) - rs * sp) * cos_deg(da), y);
+
+    (modulo(right_ascension + da, 360.0), d)
+}
+
+// Get the atmospheric refraction in degrees for a true altitude, with the
+// refraction below one degree under the horizon left out
+pub fn get_refraction(altitude: f64) -> f64 {
+    if altitude < -1.0 {
+        return 0.0;
+    }
+
+    // (formula 16.4)
+    // NOTE: The constant term makes the refraction vanish at the zenith
+    let r = 1.02 / tan_deg(altitude + 10.3 / (altitude + 5.11)) + 0.001_927_9;
+
+    r.max(0.0) / 60.0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn get_sidereal_time_test() {
+        // Example 12.a from "Astronomical Algoritms"
+        assert_approx_eq!(dec_deg(13.0, 10.0, 46.3668) * 15.0, get_sidereal_time(2_446_895.5), 0.000_01);
+    }
+
+    #[test]
+    fn get_horizontal_coordinates_test() {
+        // Example 13.b from "Astronomical Algoritms" with the apparent
+        // sidereal time at Greenwich, and the azimuth measured from the
+        // north
+        let observer = Observer::new(dec_deg(38.0, 55.0, 17.0), -dec_deg(77.0, 3.0, 56.0), 0.0);
+        let th0 = dec_deg(8.0, 34.0, 56.853) * 15.0;
+        let (a, h) = get_horizontal_coordinates(&observer, th0, 347.319_337_5, -6.719_891_7);
+        assert_approx_eq!(68.0337 + 180.0, a, 0.001);
+        assert_approx_eq!(15.1249, h, 0.001);
+    }
+
+    #[test]
+    fn get_topocentric_coordinates_test() {
+        // Example 40.a from "Astronomical Algoritms" with the sidereal time
+        // at Greenwich giving the hour angle of the example
+        let observer = Observer::new(dec_deg(33.0, 21.0, 22.0), -dec_deg(116.0, 51.0, 45.0), 1706.0);
+        let a = dec_deg(22.0, 38.0, 7.25) * 15.0;
+        let d = -dec_deg(15.0, 46.0, 15.9);
+        let th0 = modulo(288.7958 - observer.longitude() + a, 360.0);
+        let (a, d) = get_topocentric_coordinates(&observer, th0, a, d, dec_deg(0.0, 0.0, 23.592));
+        assert_approx_eq!(dec_deg(22.0, 38.0, 8.54) * 15.0, a, 0.000_1);
+        assert_approx_eq!(-dec_deg(15.0, 46.0, 30.0), d, 0.000_1);
+    }
+
+    #[test]
+    fn get_refraction_test() {
+        // The refraction is about 29' on the horizon
+        assert_approx_eq!(28.98 / 60.0, get_refraction(0.0), 0.001);
+        assert_approx_eq!(0.0, get_refraction(90.0), 0.000_01);
+        assert_eq!(0.0, get_refraction(-2.0));
+    }
+}
@@ -1,20 +1,102 @@
 use julian::*;
+use error::Error;
 
+use alloc::vec::Vec;
 #[cfg(not(feature = "std"))]
 use num_traits::Float;
 
+/// Observed or predicted values of ΔT in seconds for decimal years
+///
+/// The values are interpolated linearly between the years of the table.
+#[derive(Clone, Debug, PartialEq)]
+pub struct DeltaTimeTable {
+    values: Vec<(f64, f64)>
+}
+
+impl DeltaTimeTable {
+    pub fn new(values: Vec<(f64, f64)>) -> DeltaTimeTable {
+        DeltaTimeTable::try_new(values).unwrap()
+    }
+
+    /// Create a table from values of ΔT for decimal years
+    ///
+    /// The years must be strictly increasing and the values finite.
+    pub fn try_new(values: Vec<(f64, f64)>) -> Result<DeltaTimeTable, Error> {
+        if values.iter().any(|&(y, dt)| !y.is_finite() || !dt.is_finite()) {
+            return Err(Error::InvalidDeltaTimeTable);
+        }
+        if values.windows(2).any(|w| w[0].0 >= w[1].0) {
+            return Err(Error::InvalidDeltaTimeTable);
+        }
+        Ok(DeltaTimeTable { values })
+    }
+
+    /// Interpolate the value of ΔT for a year covered by the table
+    pub fn get(&self, year: f64) -> Option<f64> {
+        let i = self.values.partition_point(|&(y, _)| y <= year);
+        if i == 0 {
+            return None;
+        }
+        let (y0, dt0) = self.values[i - 1];
+        if i == self.values.len() {
+            return if year == y0 { Some(dt0) } else { None };
+        }
+        let (y1, dt1) = self.values[i];
+
+        Some(dt0 + (dt1 - dt0) * (year - y0) / (y1 - y0))
+    }
+}
+
 pub fn delta_time(year: f64) -> f64 {
-    // From "Polynomial Expressions for Delta T"
-    // By Fred Espenak, GSFC Planetary Systems Laboratory
-    // FIXME: Avoid discontinuity around 2013
-    if year >= 2013.0 {
-        let t = year - 2015.0;
-        return 67.62 + 0.3645 * t + 0.0039755 * t.powi(2)
+    try_delta_time(year).unwrap()
+}
+
+pub fn try_delta_time(year: f64) -> Result<f64, Error> {
+    try_delta_time_with_table(year, None)
+}
+
+/// Get ΔT in seconds for a decimal year
+///
+/// The value of the table is used for the years it covers, and the built-in
+/// estimate for the other years.
+pub fn try_delta_time_with_table(year: f64, table: Option<&DeltaTimeTable>) -> Result<f64, Error> {
+    if !(-1999.0..=3000.0).contains(&year) {
+        return Err(Error::OutOfRange);
     }
 
-    // From "Delta T: Polynomial Approximation of Time Period 1620-2013"
-    // By M. Khalid, Mariam Sultana, and Faheem Zaidi (2014)
-    let terms = vec![
+    if let Some(dt) = table.and_then(|table| table.get(year)) {
+        return Ok(dt);
+    }
+
+    // The estimates are blended where they meet to avoid discontinuities
+    let dt = if year < 1620.0 {
+        historical_delta_time(year)
+    } else if year < 1660.0 {
+        blend(year, 1620.0, 1660.0, historical_delta_time(year), modern_delta_time(year))
+    } else if year < 2012.0 {
+        modern_delta_time(year)
+    } else if year < 2016.0 {
+        blend(year, 2012.0, 2016.0, modern_delta_time(year), future_delta_time(year))
+    } else {
+        future_delta_time(year)
+    };
+
+    Ok(dt)
+}
+
+// Move from one estimate to another with a weight going smoothly from 0 to 1
+// between two years
+fn blend(year: f64, start: f64, end: f64, a: f64, b: f64) -> f64 {
+    let x = ((year - start) / (end - start)).clamp(0.0, 1.0);
+    let w = x * x * (3.0 - 2.0 * x);
+
+    a * (1.0 - w) + b * w
+}
+
+// From "Delta T: Polynomial Approximation of Time Period 1620-2013"
+// By M. Khalid, Mariam Sultana, and Faheem Zaidi (2014)
+fn modern_delta_time(year: f64) -> f64 {
+    let terms = [
         (3.670, 76.541, -253.532,  695.901, -1256.982,   627.152),
         (3.120, 10.872,  -40.744,  236.890,  -351.537,    36.612),
         (2.495, 13.480,   13.075,    8.635,    -3.307,  -128.294),
@@ -26,7 +108,7 @@ pub fn delta_time(year: f64) -> f64 {
         (0.115, 55.281,   91.248,   87.202, -3092.565,  8255.422)
     ];
     let (k, a0, a1, a2, a3, a4) = match year.round() as i64 {
-        1620 ..= 1672 => terms[0],
+        ..= 1672      => terms[0],
         1673 ..= 1729 => terms[1],
         1730 ..= 1797 => terms[2],
         1798 ..= 1843 => terms[3],
@@ -34,8 +116,7 @@ pub fn delta_time(year: f64) -> f64 {
         1878 ..= 1904 => terms[5],
         1905 ..= 1945 => terms[6],
         1946 ..= 1989 => terms[7],
-        1990 ..= 2013 => terms[8],
-        _             => panic!("year too far back in time")
+        _             => terms[8]
     };
     let u = k + (year - 2000.0) / 100.0;
 
@@ -45,8 +126,64 @@ pub fn delta_time(year: f64) -> f64 {
        + a4 * u.powi(4)
 }
 
+// From "Polynomial Expressions for Delta T"
+// By Fred Espenak, GSFC Planetary Systems Laboratory
+fn future_delta_time(year: f64) -> f64 {
+    let t = year - 2015.0;
+    let dt = 67.62 + 0.3645 * t + 0.0039755 * t.powi(2);
+    if year < 2050.0 {
+        return dt;
+    }
+
+    // From "Five Millennium Canon of Solar Eclipses: -1999 to +3000"
+    // By Fred Espenak and Jean Meeus (2006)
+    let long_term_dt = long_term_delta_time(year) - 0.5628 * (2150.0 - year).max(0.0);
+    blend(year, 2050.0, 2100.0, dt, long_term_dt)
+}
+
+// From "Five Millennium Canon of Solar Eclipses: -1999 to +3000"
+// By Fred Espenak and Jean Meeus (2006)
+fn historical_delta_time(year: f64) -> f64 {
+    if year < -500.0 {
+        return long_term_delta_time(year);
+    }
+    if year >= 1600.0 {
+        let t = year - 1600.0;
+        return 120.0 - 0.9808 * t - 0.01532 * t.powi(2) + t.powi(3) / 7129.0;
+    }
+
+    let terms = [
+        (   0.0, 10583.6, -1014.41, 33.78311, -5.952053, -0.1798452,  0.022174192, 0.0090316521),
+        (1000.0,  1574.2,  -556.01, 71.23472,  0.319781, -0.8503463, -0.005050998, 0.0083572073)
+    ];
+    let (y0, a0, a1, a2, a3, a4, a5, a6) = if year < 500.0 { terms[0] } else { terms[1] };
+    let u = (year - y0) / 100.0;
+
+    a0 + a1 * u
+       + a2 * u.powi(2)
+       + a3 * u.powi(3)
+       + a4 * u.powi(4)
+       + a5 * u.powi(5)
+       + a6 * u.powi(6)
+}
+
+// Long-term parabola from "Historical values of the Earth's clock error"
+// By L. V. Morrison and F. R. Stephenson (2004)
+fn long_term_delta_time(year: f64) -> f64 {
+    let u = (year - 1820.0) / 100.0;
+    -20.0 + 32.0 * u.powi(2)
+}
+
 pub fn terrestrial_to_universal_time(timestamp: i64) -> i64 {
-    timestamp - delta_time(unix_to_year(timestamp)).floor() as i64
+    try_terrestrial_to_universal_time(timestamp).unwrap()
+}
+
+pub fn try_terrestrial_to_universal_time(timestamp: i64) -> Result<i64, Error> {
+    try_terrestrial_to_universal_time_with_table(timestamp, None)
+}
+
+pub fn try_terrestrial_to_universal_time_with_table(timestamp: i64, table: Option<&DeltaTimeTable>) -> Result<i64, Error> {
+    Ok(timestamp - try_delta_time_with_table(unix_to_year(timestamp), table)?.floor() as i64)
 }
 
 #[cfg(test)]
@@ -75,4 +212,60 @@ mod tests {
         assert_approx_eq!(70.0000, delta_time(unix_to_year(1577836800)), 1.0); // 2020-01-01
         assert_approx_eq!(72.0000, delta_time(unix_to_year(1704067200)), 1.0); // 2024-01-01
     }
+
+    #[test]
+    fn delta_time_long_term_test() {
+        // https://eclipse.gsfc.nasa.gov/SEhelp/deltat2004.html
+        assert_approx_eq!(46_780.0, delta_time(-1999.0), 500.0);
+        assert_approx_eq!(17_190.0, delta_time(-500.0), 430.0);
+        assert_approx_eq!(10_580.0, delta_time(0.0), 260.0);
+        assert_approx_eq!( 5_710.0, delta_time(500.0), 160.0);
+        assert_approx_eq!( 1_570.0, delta_time(1000.0), 55.0);
+        assert_approx_eq!(   200.0, delta_time(1500.0), 20.0);
+        assert_approx_eq!(   120.0, delta_time(1600.0), 20.0);
+        assert_approx_eq!(   203.0, delta_time(2100.0), 5.0);
+        assert_approx_eq!( 4_435.0, delta_time(3000.0), 5.0);
+    }
+
+    #[test]
+    fn delta_time_continuity_test() {
+        for &year in [1620.0, 1660.0, 2012.0, 2013.0, 2016.0, 2050.0, 2100.0, 2150.0].iter() {
+            assert_approx_eq!(delta_time(year - 0.000001), delta_time(year + 0.000001), 0.001);
+        }
+    }
+
+    #[test]
+    fn delta_time_table_test() {
+        let table = DeltaTimeTable::new(vec![
+            (2900.0, 4000.0),
+            (2901.0, 4010.0),
+            (2902.0, 4030.0)
+        ]);
+        assert_eq!(None, table.get(2899.9));
+        assert_eq!(Some(4000.0), table.get(2900.0));
+        assert_eq!(Some(4005.0), table.get(2900.5));
+        assert_eq!(Some(4020.0), table.get(2901.5));
+        assert_eq!(Some(4030.0), table.get(2902.0));
+        assert_eq!(None, table.get(2902.1));
+
+        assert_eq!(Ok(delta_time(2899.0)), try_delta_time_with_table(2899.0, Some(&table)));
+        assert_eq!(Ok(4020.0), try_delta_time_with_table(2901.5, Some(&table)));
+        assert_eq!(Err(Error::OutOfRange), try_delta_time_with_table(3001.0, Some(&table)));
+
+        // The years must be strictly increasing
+        assert!(DeltaTimeTable::try_new(vec![]).is_ok());
+        assert_eq!(Err(Error::InvalidDeltaTimeTable), DeltaTimeTable::try_new(vec![(2900.0, 4000.0), (2900.0, 4010.0)]));
+        assert_eq!(Err(Error::InvalidDeltaTimeTable), DeltaTimeTable::try_new(vec![(2901.0, 4000.0), (2900.0, 4010.0)]));
+        assert_eq!(Err(Error::InvalidDeltaTimeTable), DeltaTimeTable::try_new(vec![(f64::NAN, 4000.0)]));
+    }
+
+    #[test]
+    fn try_delta_time_test() {
+        assert!(try_delta_time(1600.0).is_ok());
+        assert!(try_delta_time(-1999.0).is_ok());
+        assert!(try_delta_time(3000.0).is_ok());
+        assert_eq!(Err(Error::OutOfRange), try_delta_time(-2000.0));
+        assert_eq!(Err(Error::OutOfRange), try_delta_time(3001.0));
+        assert_eq!(Err(Error::OutOfRange), try_terrestrial_to_universal_time(-130000000000));
+    }
 }
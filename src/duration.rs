@@ -0,0 +1,268 @@
+use error::Error;
+use format::*;
+
+use alloc::string::String;
+use core::convert::TryFrom;
+use core::fmt;
+use core::ops::{Add, AddAssign, Mul, Neg, Sub, SubAssign};
+use core::time::Duration;
+
+const NANOSECONDS_PER_SECOND: i128 = 1_000_000_000;
+const NANOSECONDS_PER_DIMIDAY: i128 = 8_640_000_000;
+const NANOSECONDS_PER_CENTIDAY: i128 = 100 * NANOSECONDS_PER_DIMIDAY;
+const NANOSECONDS_PER_DAY: i128 = 100 * NANOSECONDS_PER_CENTIDAY;
+
+const DEFAULT_FORMAT: &str = "%-dd %-cc %-bb";
+
+/// A span of time in metric units of the mean solar day
+///
+/// A day is divided into 100 centidays of 100 dimidays, so that a dimiday
+/// lasts 8.64 seconds. The duration is kept to the nanosecond and can be
+/// negative.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct MetricDuration {
+    nanoseconds: i128
+}
+
+impl MetricDuration {
+    pub const ZERO: MetricDuration = MetricDuration { nanoseconds: 0 };
+
+    pub const fn from_days(days: i64) -> MetricDuration {
+        MetricDuration { nanoseconds: days as i128 * NANOSECONDS_PER_DAY }
+    }
+
+    pub const fn from_centidays(centidays: i64) -> MetricDuration {
+        MetricDuration { nanoseconds: centidays as i128 * NANOSECONDS_PER_CENTIDAY }
+    }
+
+    pub const fn from_dimidays(dimidays: i64) -> MetricDuration {
+        MetricDuration { nanoseconds: dimidays as i128 * NANOSECONDS_PER_DIMIDAY }
+    }
+
+    pub const fn from_seconds(seconds: i64) -> MetricDuration {
+        MetricDuration { nanoseconds: seconds as i128 * NANOSECONDS_PER_SECOND }
+    }
+
+    pub const fn from_nanoseconds(nanoseconds: i128) -> MetricDuration {
+        MetricDuration { nanoseconds }
+    }
+
+    /// Get the number of whole days, rounded toward zero
+    pub fn days(&self) -> i64 {
+        (self.nanoseconds / NANOSECONDS_PER_DAY) as i64
+    }
+
+    /// Get the number of whole centidays, rounded toward zero
+    pub fn centidays(&self) -> i64 {
+        (self.nanoseconds / NANOSECONDS_PER_CENTIDAY) as i64
+    }
+
+    /// Get the number of whole dimidays, rounded toward zero
+    pub fn dimidays(&self) -> i64 {
+        (self.nanoseconds / NANOSECONDS_PER_DIMIDAY) as i64
+    }
+
+    /// Get the number of whole seconds, rounded toward zero
+    pub fn seconds(&self) -> i64 {
+        (self.nanoseconds / NANOSECONDS_PER_SECOND) as i64
+    }
+
+    pub fn nanoseconds(&self) -> i128 {
+        self.nanoseconds
+    }
+
+    pub fn as_seconds_f64(&self) -> f64 {
+        self.nanoseconds as f64 / NANOSECONDS_PER_SECOND as f64
+    }
+
+    pub fn is_negative(&self) -> bool {
+        self.nanoseconds < 0
+    }
+
+    pub fn abs(&self) -> MetricDuration {
+        MetricDuration { nanoseconds: self.nanoseconds.abs() }
+    }
+
+    pub fn checked_add(&self, other: MetricDuration) -> Option<MetricDuration> {
+        self.nanoseconds.checked_add(other.nanoseconds).map(MetricDuration::from_nanoseconds)
+    }
+
+    pub fn checked_sub(&self, other: MetricDuration) -> Option<MetricDuration> {
+        self.nanoseconds.checked_sub(other.nanoseconds).map(MetricDuration::from_nanoseconds)
+    }
+
+    pub fn checked_mul(&self, n: i64) -> Option<MetricDuration> {
+        self.nanoseconds.checked_mul(n as i128).map(MetricDuration::from_nanoseconds)
+    }
+
+    /// Get a string representation of a duration
+    ///
+    /// See `try_format` for the format.
+    pub fn format(&self, format: &str) -> String {
+        self.try_format(format).unwrap()
+    }
+
+    /// Get a string representation of a duration
+    ///
+    /// %d Days
+    /// %c Centidays of the last day
+    /// %b Dimidays of the last centiday
+    /// %f Decimals of the last dimiday, with the width giving the precision
+    /// %% Literal percent sign
+    ///
+    /// The flags and widths are the same as in `get_formatted_date`, and
+    /// the sign of a negative duration is printed before the first field.
+    pub fn try_format(&self, format: &str) -> Result<String, Error> {
+        let tokens = parse_format(format)?;
+        let n = self.nanoseconds.abs();
+
+        let mut is_signed = !self.is_negative();
+        let mut res = String::new();
+        for token in &tokens {
+            let field = match *token {
+                Token::Literal(ref literal) => {
+                    res.push_str(literal);
+                    continue;
+                },
+                Token::Field(field) => field
+            };
+            if !is_signed {
+                res.push('-');
+                is_signed = true;
+            }
+            let n = match field.specifier {
+                'd' => n / NANOSECONDS_PER_DAY,
+                'c' => n / NANOSECONDS_PER_CENTIDAY % 100,
+                'b' => n / NANOSECONDS_PER_DIMIDAY % 100,
+                'f' => {
+                    // The remainder of a dimiday is scaled down to fit the
+                    // numerator of the decimals
+                    let r = (n % NANOSECONDS_PER_DIMIDAY) / 1_000;
+                    let precision = field.width.unwrap_or(2);
+                    res.push_str(&format_decimals(r as i64, (NANOSECONDS_PER_DIMIDAY / 1_000) as i64, precision));
+                    continue;
+                },
+                specifier => return Err(Error::UnknownSpecifier(specifier))
+            };
+            res.push_str(&format_number(n as i64, &field, 2));
+        }
+
+        Ok(res)
+    }
+}
+
+impl fmt::Display for MetricDuration {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.format(DEFAULT_FORMAT))
+    }
+}
+
+impl From<Duration> for MetricDuration {
+    fn from(duration: Duration) -> MetricDuration {
+        MetricDuration { nanoseconds: duration.as_nanos() as i128 }
+    }
+}
+
+/// Get the duration of a positive metric duration
+impl TryFrom<MetricDuration> for Duration {
+    type Error = Error;
+
+    fn try_from(duration: MetricDuration) -> Result<Duration, Error> {
+        let n = u128::try_from(duration.nanoseconds).map_err(|_| Error::OutOfRange)?;
+        let seconds = u64::try_from(n / NANOSECONDS_PER_SECOND as u128).map_err(|_| Error::OutOfRange)?;
+
+        Ok(Duration::new(seconds, (n % NANOSECONDS_PER_SECOND as u128) as u32))
+    }
+}
+
+impl Add for MetricDuration {
+    type Output = MetricDuration;
+
+    fn add(self, other: MetricDuration) -> MetricDuration {
+        self.checked_add(other).expect("overflow when adding durations")
+    }
+}
+
+impl Sub for MetricDuration {
+    type Output = MetricDuration;
+
+    fn sub(self, other: MetricDuration) -> MetricDuration {
+        self.checked_sub(other).expect("overflow when subtracting durations")
+    }
+}
+
+impl Mul<i64> for MetricDuration {
+    type Output = MetricDuration;
+
+    fn mul(self, n: i64) -> MetricDuration {
+        self.checked_mul(n).expect("overflow when multiplying duration")
+    }
+}
+
+impl Neg for MetricDuration {
+    type Output = MetricDuration;
+
+    fn neg(self) -> MetricDuration {
+        MetricDuration { nanoseconds: -self.nanoseconds }
+    }
+}
+
+impl AddAssign for MetricDuration {
+    fn add_assign(&mut self, other: MetricDuration) {
+        *self = *self + other;
+    }
+}
+
+impl SubAssign for MetricDuration {
+    fn sub_assign(&mut self, other: MetricDuration) {
+        *self = *self - other;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::string::ToString;
+
+    #[test]
+    fn metric_duration_test() {
+        let duration = MetricDuration::from_days(3) + MetricDuration::from_centidays(42) + MetricDuration::from_dimidays(17);
+        assert_eq!(3, duration.days());
+        assert_eq!(342, duration.centidays());
+        assert_eq!(34217, duration.dimidays());
+        assert_eq!(295634, duration.seconds());
+        assert_eq!(MetricDuration::from_seconds(864), MetricDuration::from_centidays(1));
+        assert_eq!(-3, (-duration).days());
+        assert_eq!(duration, (-duration).abs());
+
+        let mut d = MetricDuration::ZERO;
+        d += MetricDuration::from_days(2);
+        d -= MetricDuration::from_dimidays(1);
+        assert_eq!(MetricDuration::from_dimidays(19999), d);
+        assert_eq!(MetricDuration::from_days(6), MetricDuration::from_days(2) * 3);
+        assert_eq!(None, MetricDuration::from_nanoseconds(i128::MAX).checked_add(MetricDuration::from_seconds(1)));
+    }
+
+    #[test]
+    fn metric_duration_conversion_test() {
+        let duration = MetricDuration::from(Duration::new(864, 5));
+        assert_eq!(MetricDuration::from_centidays(1) + MetricDuration::from_nanoseconds(5), duration);
+        assert_eq!(Ok(Duration::new(864, 5)), Duration::try_from(duration));
+        assert_eq!(Err(Error::OutOfRange), Duration::try_from(-duration));
+        assert_eq!(8.64, MetricDuration::from_dimidays(1).as_seconds_f64());
+    }
+
+    #[test]
+    fn metric_duration_format_test() {
+        let duration = MetricDuration::from_days(3) + MetricDuration::from_centidays(42) + MetricDuration::from_dimidays(17);
+        assert_eq!("3d 42c 17b", duration.to_string());
+        assert_eq!("-3d 42c 17b", (-duration).to_string());
+        assert_eq!("0d 0c 5b", MetricDuration::from_dimidays(5).to_string());
+        assert_eq!("03:42:17", duration.format("%d:%c:%b"));
+
+        let duration = MetricDuration::from_dimidays(1) + MetricDuration::from_seconds(2);
+        assert_eq!("01.2314", duration.format("%b.%4f"));
+        assert_eq!("100%", MetricDuration::from_days(100).format("%-d%%"));
+        assert_eq!(Err(Error::UnknownSpecifier('y')), duration.try_format("%y"));
+    }
+}
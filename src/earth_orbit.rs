@@ -1,20 +1,22 @@
 use math::*;
 use julian::*;
 use delta_time::*;
+use error::Error;
+use timestamp::Timestamp;
 
 #[cfg(not(feature = "std"))]
 use num_traits::Float;
 
 #[repr(usize)]
 #[derive(Clone, Copy)]
-enum Event {
+pub(crate) enum Event {
     MarchEquinox,
     JuneSolstice,
     SeptemberEquinox,
     DecemberSolstice
 }
 
-fn get_time_of(event: Event, timestamp: i64) -> i64 {
+fn get_time_of(event: Event, timestamp: i64, table: Option<&DeltaTimeTable>) -> Result<i64, Error> {
     let jd = unix_to_julian(timestamp);
 
     let y = jde_to_julian_year(jd).floor();
@@ -31,12 +33,13 @@ fn get_time_of(event: Event, timestamp: i64) -> i64 {
     let s = get_periodic_terms(t);
 
     // FIXME: Improve the accuracy
-    terrestrial_to_universal_time(julian_to_unix(jdme + (0.00001 * s) / l))
+    try_terrestrial_to_universal_time_with_table(julian_to_unix(jdme + (0.00001 * s) / l), table)
 }
 
+#[allow(clippy::inconsistent_digit_grouping)]
 fn get_jdme(event: Event, y: f64) -> f64 {
     // For the years -1000 to +1000
-    let jdme_terms_before_1000 = vec![
+    let jdme_terms_before_1000 = [
         (1721_139.29189, 365_242.13740,  0.06134,  0.00111, -0.00071), // March Equinoxe
         (1721_233.25401, 365_241.72562, -0.05323,  0.00907, -0.00025), // June Solstice
         (1721_325.70455, 365_242.49558, -0.11677, -0.00297,  0.00074), // September Equinoxe
@@ -44,7 +47,7 @@ fn get_jdme(event: Event, y: f64) -> f64 {
     ];
     
     // For the years +1000 to +3000
-    let jdme_terms_after_1000 = vec![
+    let jdme_terms_after_1000 = [
         (2451_623.80984, 365_242.37404,  0.05169, -0.00411, -0.00057), // March Equinoxe
         (2451_716.56767, 365_241.62603,  0.00325,  0.00888, -0.00030), // June Solstice
         (2451_810.21715, 365_242.01767, -0.11575,  0.00337,  0.00078), // September Equinoxe
@@ -99,56 +102,107 @@ fn get_periodic_terms(t: f64) -> f64 {
     })
 }
 
-fn get_previous_time_of(event: Event, timestamp: i64) -> i64 {
-    let time_of_event = get_time_of(event, timestamp);
-    if time_of_event >= timestamp {
-        let delta = (365.25 * 86400.0) as i64;
-        get_time_of(event, timestamp - delta)
-    } else {
-        time_of_event
+// The events are computed for the julian year of the timestamp, but julian
+// years drift from the seasons by about a month over the supported range, so
+// the event of an adjacent year can be the right one.
+pub(crate) fn get_previous_time_of(event: Event, timestamp: i64, table: Option<&DeltaTimeTable>) -> Result<i64, Error> {
+    let delta = (365.25 * 86400.0) as i64;
+    let mut t = timestamp;
+    let mut time_of_event = get_time_of(event, t, table)?;
+    while let Ok(next_time_of_event) = get_time_of(event, t + delta, table) {
+        if next_time_of_event >= timestamp {
+            break;
+        }
+        t += delta;
+        time_of_event = next_time_of_event;
     }
+    while time_of_event >= timestamp {
+        t -= delta;
+        time_of_event = get_time_of(event, t, table)?;
+    }
+    Ok(time_of_event)
 }
 
-fn get_next_time_of(event: Event, timestamp: i64) -> i64 {
-    let time_of_event = get_time_of(event, timestamp);
-    if time_of_event <= timestamp {
-        let delta = (365.25 * 86400.0) as i64;
-        get_time_of(event, timestamp + delta)
-    } else {
-        time_of_event
+pub(crate) fn get_next_time_of(event: Event, timestamp: i64, table: Option<&DeltaTimeTable>) -> Result<i64, Error> {
+    let delta = (365.25 * 86400.0) as i64;
+    let mut t = timestamp;
+    let mut time_of_event = get_time_of(event, t, table)?;
+    while let Ok(previous_time_of_event) = get_time_of(event, t - delta, table) {
+        if previous_time_of_event <= timestamp {
+            break;
+        }
+        t -= delta;
+        time_of_event = previous_time_of_event;
+    }
+    while time_of_event <= timestamp {
+        t += delta;
+        time_of_event = get_time_of(event, t, table)?;
     }
+    Ok(time_of_event)
+}
+
+pub fn get_previous_march_equinox<T: Timestamp>(timestamp: T) -> i64 {
+    try_get_previous_march_equinox(timestamp).unwrap()
+}
+
+pub fn try_get_previous_march_equinox<T: Timestamp>(timestamp: T) -> Result<i64, Error> {
+    get_previous_time_of(Event::MarchEquinox, timestamp.unix_timestamp(), None)
+}
+
+pub fn get_next_march_equinox<T: Timestamp>(timestamp: T) -> i64 {
+    try_get_next_march_equinox(timestamp).unwrap()
+}
+
+pub fn try_get_next_march_equinox<T: Timestamp>(timestamp: T) -> Result<i64, Error> {
+    get_next_time_of(Event::MarchEquinox, timestamp.unix_timestamp(), None)
+}
+
+pub fn get_previous_june_solstice<T: Timestamp>(timestamp: T) -> i64 {
+    try_get_previous_june_solstice(timestamp).unwrap()
+}
+
+pub fn try_get_previous_june_solstice<T: Timestamp>(timestamp: T) -> Result<i64, Error> {
+    get_previous_time_of(Event::JuneSolstice, timestamp.unix_timestamp(), None)
+}
+
+pub fn get_next_june_solstice<T: Timestamp>(timestamp: T) -> i64 {
+    try_get_next_june_solstice(timestamp).unwrap()
 }
 
-pub fn get_previous_march_equinox(timestamp: i64) -> i64 {
-    get_previous_time_of(Event::MarchEquinox, timestamp)
+pub fn try_get_next_june_solstice<T: Timestamp>(timestamp: T) -> Result<i64, Error> {
+    get_next_time_of(Event::JuneSolstice, timestamp.unix_timestamp(), None)
 }
 
-pub fn get_next_march_equinox(timestamp: i64) -> i64 {
-    get_next_time_of(Event::MarchEquinox, timestamp)
+pub fn get_previous_september_equinox<T: Timestamp>(timestamp: T) -> i64 {
+    try_get_previous_september_equinox(timestamp).unwrap()
 }
 
-pub fn get_previous_june_solstice(timestamp: i64) -> i64 {
-    get_previous_time_of(Event::JuneSolstice, timestamp)
+pub fn try_get_previous_september_equinox<T: Timestamp>(timestamp: T) -> Result<i64, Error> {
+    get_previous_time_of(Event::SeptemberEquinox, timestamp.unix_timestamp(), None)
 }
 
-pub fn get_next_june_solstice(timestamp: i64) -> i64 {
-    get_next_time_of(Event::JuneSolstice, timestamp)
+pub fn get_next_september_equinox<T: Timestamp>(timestamp: T) -> i64 {
+    try_get_next_september_equinox(timestamp).unwrap()
 }
 
-pub fn get_previous_september_equinox(timestamp: i64) -> i64 {
-    get_previous_time_of(Event::SeptemberEquinox, timestamp)
+pub fn try_get_next_september_equinox<T: Timestamp>(timestamp: T) -> Result<i64, Error> {
+    get_next_time_of(Event::SeptemberEquinox, timestamp.unix_timestamp(), None)
 }
 
-pub fn get_next_september_equinox(timestamp: i64) -> i64 {
-    get_next_time_of(Event::SeptemberEquinox, timestamp)
+pub fn get_previous_december_solstice<T: Timestamp>(timestamp: T) -> i64 {
+    try_get_previous_december_solstice(timestamp).unwrap()
 }
 
-pub fn get_previous_december_solstice(timestamp: i64) -> i64 {
-    get_previous_time_of(Event::DecemberSolstice, timestamp)
+pub fn try_get_previous_december_solstice<T: Timestamp>(timestamp: T) -> Result<i64, Error> {
+    get_previous_time_of(Event::DecemberSolstice, timestamp.unix_timestamp(), None)
 }
 
-pub fn get_next_december_solstice(timestamp: i64) -> i64 {
-    get_next_time_of(Event::DecemberSolstice, timestamp)
+pub fn get_next_december_solstice<T: Timestamp>(timestamp: T) -> i64 {
+    try_get_next_december_solstice(timestamp).unwrap()
+}
+
+pub fn try_get_next_december_solstice<T: Timestamp>(timestamp: T) -> Result<i64, Error> {
+    get_next_time_of(Event::DecemberSolstice, timestamp.unix_timestamp(), None)
 }
 
 #[cfg(test)]
@@ -193,4 +247,13 @@ mod tests {
             assert_approx_eq!(solstice_december_2013, get_next_december_solstice(t), accuracy);
         }
     }
+
+    #[test]
+    fn try_get_next_december_solstice_test() {
+        let t = parse_time("2013-06-01T00:00:00.00+00:00");
+        assert_eq!(Ok(get_next_december_solstice(t)), try_get_next_december_solstice(t));
+
+        let t = parse_time("3500-06-01T00:00:00.00+00:00");
+        assert_eq!(Err(Error::OutOfRange), try_get_next_december_solstice(t));
+    }
 }
@@ -2,92 +2,270 @@ use sun_transit::*;
 use earth_orbit::*;
 use moon_phase::*;
 use moon_transit::*;
+use observer::Observer;
+use timestamp::Timestamp;
 
 use alloc::collections::BTreeMap;
 use alloc::string::ToString;
 use alloc::string::String;
+use alloc::vec::Vec;
+use core::fmt;
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+/// An event of the ephemeris
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum Event {
+    Current,
+    Equinox,
+    Solstice,
+    NewMoon,
+    FirstQuarterMoon,
+    FullMoon,
+    LastQuarterMoon,
+    Moonrise,
+    /// Moonrise of the day found from the next day, when the moonrise found
+    /// from the timestamp happens before the beginning of the day
+    NextMoonrise,
+    /// Moonrise of the day found from the previous day, when the moonrise
+    /// found from the timestamp happens after the end of the day
+    PreviousMoonrise,
+    Moonset,
+    Sunrise,
+    Sunset,
+    CivilDawn,
+    CivilDusk,
+    NauticalDawn,
+    NauticalDusk,
+    AstronomicalDawn,
+    AstronomicalDusk
+}
+
+impl fmt::Display for Event {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            Event::Current          => "Current",
+            Event::Equinox          => "Equinox",
+            Event::Solstice         => "Solstice",
+            Event::NewMoon          => "New Moon",
+            Event::FirstQuarterMoon => "First Quarter Moon",
+            Event::FullMoon         => "Full Moon",
+            Event::LastQuarterMoon  => "Last Quarter Moon",
+            Event::Moonrise         => "Moonrise",
+            Event::NextMoonrise     => "Moonrise+1",
+            Event::PreviousMoonrise => "Moonrise-1",
+            Event::Moonset          => "Moonset",
+            Event::Sunrise          => "Sunrise",
+            Event::Sunset           => "Sunset",
+            Event::CivilDawn        => "Civil Dawn",
+            Event::CivilDusk        => "Civil Dusk",
+            Event::NauticalDawn     => "Nautical Dawn",
+            Event::NauticalDusk     => "Nautical Dusk",
+            Event::AstronomicalDawn => "Astronomical Dawn",
+            Event::AstronomicalDusk => "Astronomical Dusk"
+        };
+        write!(f, "{}", name)
+    }
+}
+
+/// The ephemeris of a geodate seen by an observer
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Ephemeris {
+    /// Events of the day sorted by time
+    pub events: Vec<(i64, Event)>,
+    /// Phase, illumination, and age of the Moon at the time of the geodate
+    pub moon: MoonIllumination
+}
 
 /// Get the ephemeris of a geodate
-pub fn get_ephemeris(timestamp: i64, longitude: f64, latitude: f64) -> BTreeMap<i64, String> {
-    let mut events = BTreeMap::new();
+pub fn get_ephemeris<T: Timestamp>(timestamp: T, longitude: f64, latitude: f64) -> BTreeMap<i64, String> {
+    get_ephemeris_events(timestamp, longitude, latitude).into_iter().map(|(time, event)| {
+        (time, event.to_string())
+    }).collect()
+}
+
+/// Get the ephemeris of a geodate seen by an observer
+pub fn get_ephemeris_with_observer<T: Timestamp>(timestamp: T, observer: &Observer) -> BTreeMap<i64, String> {
+    get_ephemeris_events_with_observer(timestamp, observer).into_iter().map(|(time, event)| {
+        (time, event.to_string())
+    }).collect()
+}
+
+/// Get the events of the ephemeris of a geodate sorted by time
+pub fn get_ephemeris_events<T: Timestamp>(timestamp: T, longitude: f64, latitude: f64) -> Vec<(i64, Event)> {
+    let observer = Observer::new_unchecked(latitude, longitude, 0.0);
+    get_ephemeris_events_with_observer(timestamp, &observer)
+}
+
+/// Get the events of the ephemeris of a geodate seen by an observer sorted
+/// by time
+///
+/// The sunrise, sunset, moonrise, and moonset are corrected for the dip
+/// of the horizon at the elevation of the observer. Events happening at
+/// the same second are all kept.
+pub fn get_ephemeris_events_with_observer<T: Timestamp>(timestamp: T, observer: &Observer) -> Vec<(i64, Event)> {
+    let timestamp = timestamp.unix_timestamp();
+    let longitude = observer.longitude();
+    let mut events = Vec::new();
 
     let day_begin_at = get_midnight(timestamp, longitude);
     let day_end_at = get_midnight(day_begin_at + 86400 + 10000, longitude);
 
-    events.insert(timestamp, "Current".to_string());
+    events.push((timestamp, Event::Current));
 
     let es = vec![
-        ("Equinox", get_next_march_equinox(day_begin_at)),
-        ("Equinox", get_next_september_equinox(day_begin_at)),
-        ("Solstice", get_next_december_solstice(day_begin_at)),
-        ("Solstice", get_next_june_solstice(day_begin_at))
+        (Event::Equinox, get_next_march_equinox(day_begin_at)),
+        (Event::Equinox, get_next_september_equinox(day_begin_at)),
+        (Event::Solstice, get_next_december_solstice(day_begin_at)),
+        (Event::Solstice, get_next_june_solstice(day_begin_at))
     ];
-    for (name, e) in es {
+    for (event, e) in es {
         if e < day_end_at {
-            events.insert(e, name.to_string());
+            events.push((e, event));
         }
     }
 
     let n = get_lunation_number(day_begin_at); // FIXME: Potential bug here
     let es = vec![
-        ("New Moon", get_new_moon(n)),
-        ("First Quarter Moon", get_first_quarter_moon(n + 0.25)),
-        ("Full Moon", get_full_moon(n + 0.50)),
-        ("Last Quarter Moon", get_last_quarter_moon(n + 0.75))
+        (Event::NewMoon, get_new_moon(n)),
+        (Event::FirstQuarterMoon, get_first_quarter_moon(n + 0.25)),
+        (Event::FullMoon, get_full_moon(n + 0.50)),
+        (Event::LastQuarterMoon, get_last_quarter_moon(n + 0.75))
     ];
-    for (name, e) in es {
+    for (event, e) in es {
         if day_begin_at < e && e < day_end_at {
-            events.insert(e, name.to_string());
+            events.push((e, event));
         }
     }
 
-    if let Some(moonrise) = get_moonrise(timestamp, longitude, latitude) {
+    if let Some(moonrise) = get_moonrise_with_observer(timestamp, observer) {
         if moonrise < day_begin_at {
-            if let Some(moonrise) = get_moonrise(timestamp + 86400, longitude, latitude) {
+            if let Some(moonrise) = get_moonrise_with_observer(timestamp + 86400, observer) {
                 if day_begin_at <= moonrise && moonrise <= day_end_at {
-                    events.insert(moonrise, "Moonrise+1".to_string());
-                } else {
-                    //events.insert(moonrise, "Moonrise +1");
+                    events.push((moonrise, Event::NextMoonrise));
                 }
             }
         } else if moonrise > day_end_at {
-            if let Some(moonrise) = get_moonrise(timestamp - 86400, longitude, latitude) {
+            if let Some(moonrise) = get_moonrise_with_observer(timestamp - 86400, observer) {
                 if day_begin_at <= moonrise && moonrise <= day_end_at {
-                    events.insert(moonrise, "Moonrise-1".to_string());
-                } else {
-                    //events.insert(moonrise, "Moonrise -1");
+                    events.push((moonrise, Event::PreviousMoonrise));
                 }
             }
         } else {
-            events.insert(moonrise, "Moonrise".to_string());
+            events.push((moonrise, Event::Moonrise));
         }
     }
 
-    if let Some(moonset) = get_moonset(timestamp, longitude, latitude) {
+    if let Some(moonset) = get_moonset_with_observer(timestamp, observer) {
         if moonset < day_begin_at {
-            if let Some(moonset) = get_moonset(timestamp + 86400, longitude, latitude) {
+            if let Some(moonset) = get_moonset_with_observer(timestamp + 86400, observer) {
                 if day_begin_at <= moonset && moonset <= day_end_at {
-                    events.insert(moonset, "Moonset".to_string());
+                    events.push((moonset, Event::Moonset));
                 }
             }
         } else if moonset > day_end_at {
-            if let Some(moonset) = get_moonset(timestamp - 86400, longitude, latitude) {
+            if let Some(moonset) = get_moonset_with_observer(timestamp - 86400, observer) {
                 if day_begin_at <= moonset && moonset <= day_end_at {
-                    events.insert(moonset, "Moonset".to_string());
+                    events.push((moonset, Event::Moonset));
                 }
             }
         } else {
-            events.insert(moonset, "Moonset".to_string());
+            events.push((moonset, Event::Moonset));
         }
     }
 
-    if let Some(sunrise) = get_sunrise(timestamp, longitude, latitude) {
-        events.insert(sunrise, "Sunrise".to_string());
+    if let Some(sunrise) = get_sunrise_with_observer(timestamp, observer) {
+        events.push((sunrise, Event::Sunrise));
     }
 
-    if let Some(sunset) = get_sunset(timestamp, longitude, latitude) {
-        events.insert(sunset, "Sunset".to_string());
+    if let Some(sunset) = get_sunset_with_observer(timestamp, observer) {
+        events.push((sunset, Event::Sunset));
+    }
+
+    let es = vec![
+        (Twilight::Civil, Event::CivilDawn, Event::CivilDusk),
+        (Twilight::Nautical, Event::NauticalDawn, Event::NauticalDusk),
+        (Twilight::Astronomical, Event::AstronomicalDawn, Event::AstronomicalDusk)
+    ];
+    for (twilight, dawn, dusk) in es {
+        if let Some(e) = get_dawn(timestamp, observer, twilight) {
+            events.push((e, dawn));
+        }
+        if let Some(e) = get_dusk(timestamp, observer, twilight) {
+            events.push((e, dusk));
+        }
     }
 
+    events.sort();
     events
 }
+
+/// Get the events of the ephemeris of a geodate seen by an observer with
+/// the phase, illumination, and age of the Moon
+pub fn get_ephemeris_with_moon<T: Timestamp>(timestamp: T, observer: &Observer) -> Ephemeris {
+    let timestamp = timestamp.unix_timestamp();
+
+    Ephemeris {
+        events: get_ephemeris_events_with_observer(timestamp, observer),
+        moon: get_moon_illumination(timestamp)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::vec::Vec;
+
+    #[test]
+    fn get_ephemeris_events_test() {
+        // Stonehenge coordinates: 51.178844, -1.826189
+        let events = get_ephemeris_events(1403322675, -1.826189, 51.178844);
+        assert!(events.contains(&(1403322675, Event::Current)));
+        assert!(events.contains(&(1403322705, Event::Sunrise)));
+        assert!(events.iter().any(|&(_, event)| event == Event::Solstice));
+
+        // There is no astronomical twilight at the june solstice
+        let dawns: Vec<Event> = events.iter().map(|&(_, e)| e).filter(|e| e.to_string().ends_with("Dawn")).collect();
+        assert_eq!(vec![Event::NauticalDawn, Event::CivilDawn], dawns);
+        let dusks: Vec<Event> = events.iter().map(|&(_, e)| e).filter(|e| e.to_string().ends_with("Dusk")).collect();
+        assert_eq!(vec![Event::CivilDusk, Event::NauticalDusk], dusks);
+
+        let ephemeris = get_ephemeris(1403322675, -1.826189, 51.178844);
+        assert_eq!(Some(&"Sunrise".to_string()), ephemeris.get(&1403322705));
+    }
+
+    #[test]
+    fn get_ephemeris_events_with_observer_test() {
+        let observer = Observer::new(51.178844, -1.826189, 0.0);
+        let events = get_ephemeris_events_with_observer(1403322675, &observer);
+        assert_eq!(get_ephemeris_events(1403322675, -1.826189, 51.178844), events);
+
+        // The sun rises earlier and sets later above sea level
+        let observer = Observer::new(51.178844, -1.826189, 500.0);
+        let others = get_ephemeris_events_with_observer(1403322675, &observer);
+        let find = |events: &Vec<(i64, Event)>, event| {
+            events.iter().find(|&&(_, e)| e == event).map(|&(t, _)| t).unwrap()
+        };
+        assert!(find(&others, Event::Sunrise) < find(&events, Event::Sunrise));
+        assert!(find(&others, Event::Sunset) > find(&events, Event::Sunset));
+        assert_eq!(Some(&"Current".to_string()), get_ephemeris_with_observer(1403322675, &observer).get(&1403322675));
+    }
+
+    #[test]
+    fn get_ephemeris_with_moon_test() {
+        let observer = Observer::new(51.178844, -1.826189, 0.0);
+        let ephemeris = get_ephemeris_with_moon(1403322675, &observer);
+        assert_eq!(get_ephemeris_events_with_observer(1403322675, &observer), ephemeris.events);
+        assert_eq!(get_moon_illumination(1403322675), ephemeris.moon);
+        assert_eq!(LunarPhase::LastQuarter, ephemeris.moon.phase);
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn event_serde_test() {
+        assert_eq!("\"NewMoon\"", serde_json::to_string(&Event::NewMoon).unwrap());
+        assert_eq!(Event::NewMoon, serde_json::from_str("\"NewMoon\"").unwrap());
+    }
+}
@@ -0,0 +1,46 @@
+use core::fmt;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Error {
+    /// The timestamp is outside of the range supported by the algorithms
+    OutOfRange,
+    /// The date string does not match the expected format
+    MalformedDate,
+    /// The field of the date string given by its specifier cannot be read
+    InvalidField(char),
+    /// The date is well formed but does not exist in the calendar
+    NonExistentDate,
+    /// The format string contains an unknown specifier
+    UnknownSpecifier(char),
+    /// The format string is incomplete
+    InvalidFormat,
+    /// The latitude of an observer is not between -90° and 90°
+    InvalidLatitude,
+    /// The longitude of an observer is not between -180° and 180°
+    InvalidLongitude,
+    /// The elevation of an observer is not finite
+    InvalidElevation,
+    /// The years of a ΔT table are not strictly increasing or its values
+    /// are not finite
+    InvalidDeltaTimeTable
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Error::OutOfRange            => write!(f, "timestamp out of range"),
+            Error::MalformedDate         => write!(f, "malformed date"),
+            Error::InvalidField(c)       => write!(f, "invalid field %{} in date", c),
+            Error::NonExistentDate       => write!(f, "non-existent date"),
+            Error::UnknownSpecifier(c)   => write!(f, "unknown specifier %{}", c),
+            Error::InvalidFormat         => write!(f, "invalid format"),
+            Error::InvalidLatitude       => write!(f, "latitude out of range"),
+            Error::InvalidLongitude      => write!(f, "longitude out of range"),
+            Error::InvalidElevation      => write!(f, "invalid elevation"),
+            Error::InvalidDeltaTimeTable => write!(f, "invalid delta time table")
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for Error {}
@@ -0,0 +1,319 @@
+use error::Error;
+use geodate::{Geodate, Epoch, Calendar, Sign, SolarTime, YearStart};
+
+use alloc::string::String;
+use alloc::vec::Vec;
+
+// Specifiers accepted in a format string
+const SPECIFIERS: &str = "hyYumsdcbflx";
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Padding {
+    Zero,
+    Space,
+    None
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Field {
+    pub specifier: char,
+    pub padding: Padding,
+    pub width: Option<usize>
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Token {
+    Literal(String),
+    Field(Field)
+}
+
+// Split a format string into literals and fields
+pub fn parse_format(format: &str) -> Result<Vec<Token>, Error> {
+    let mut tokens = Vec::new();
+    let mut literal = String::new();
+    let mut chars = format.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        if c != '%' {
+            literal.push(c);
+            continue;
+        }
+
+        let padding = match chars.peek() {
+            Some('-') => Padding::None,
+            Some('_') => Padding::Space,
+            Some('0') => Padding::Zero,
+            _         => Padding::Zero
+        };
+        if let Some('-') | Some('_') | Some('0') = chars.peek() {
+            chars.next();
+        }
+
+        let mut digits = String::new();
+        while let Some(&c) = chars.peek() {
+            if !c.is_ascii_digit() {
+                break;
+            }
+            digits.push(c);
+            chars.next();
+        }
+        let width = if digits.is_empty() {
+            None
+        } else {
+            Some(digits.parse::<usize>().map_err(|_| Error::InvalidFormat)?)
+        };
+
+        match chars.next() {
+            Some('%') => {
+                if width.is_some() {
+                    return Err(Error::InvalidFormat);
+                }
+                literal.push('%');
+            },
+            Some(specifier) if SPECIFIERS.contains(specifier) => {
+                if !literal.is_empty() {
+                    tokens.push(Token::Literal(literal.clone()));
+                    literal.clear();
+                }
+                tokens.push(Token::Field(Field { specifier, padding, width }));
+            },
+            Some(specifier) => {
+                return Err(Error::UnknownSpecifier(specifier));
+            },
+            None => {
+                return Err(Error::InvalidFormat);
+            }
+        }
+    }
+    if !literal.is_empty() {
+        tokens.push(Token::Literal(literal));
+    }
+
+    Ok(tokens)
+}
+
+// Format a number with the padding and width of a field
+pub fn format_number(n: i64, field: &Field, default_width: usize) -> String {
+    let sign = if n < 0 { "-" } else { "" };
+    let n = n.abs();
+    let width = field.width.unwrap_or(default_width);
+
+    match field.padding {
+        Padding::Zero  => format!("{}{:0width$}", sign, n, width = width),
+        Padding::Space => format!("{:>width$}", format!("{}{}", sign, n), width = width),
+        Padding::None  => format!("{}{}", sign, n)
+    }
+}
+
+// Format the decimals of a fraction given by its numerator and denominator
+pub fn format_decimals(numerator: i64, denominator: i64, precision: usize) -> String {
+    let mut res = String::new();
+    let mut r = numerator % denominator;
+    for _ in 0..precision {
+        r *= 10;
+        res.push(char::from(b'0' + (r / denominator) as u8));
+        r %= denominator;
+    }
+    res
+}
+
+// A geodate read from a string, with the timestamp given by `%x` if any
+pub struct ParsedDate {
+    pub date: Geodate,
+    pub timestamp: Option<i64>
+}
+
+// Read a geodate from a string following a format
+pub fn parse_date(format: &str, date: &str) -> Result<ParsedDate, Error> {
+    let tokens = parse_format(format)?;
+
+    let mut h = 0;
+    let mut y = 0;
+    let mut full_year = None;
+    let mut m = 0;
+    let mut d = 0;
+    let mut c = 0;
+    let mut b = 0;
+    let mut x = None;
+    let mut epoch = Epoch::Gregorian;
+    let mut calendar = Calendar::Lunisolar;
+    let mut sign = None;
+
+    let mut rest = date;
+    for token in &tokens {
+        let field = match *token {
+            Token::Literal(ref literal) => {
+                if !rest.starts_with(literal.as_str()) {
+                    return Err(Error::MalformedDate);
+                }
+                rest = &rest[literal.len()..];
+                continue;
+            },
+            Token::Field(field) => field
+        };
+
+        // The sign of the year is read before its first field
+        let is_year = field.specifier == 'h' || field.specifier == 'y' || field.specifier == 'u';
+        let is_signed = match field.specifier {
+            'Y' | 'x' => true,
+            _         => is_year && sign.is_none()
+        };
+        let max_digits = match field.specifier {
+            'Y' | 'x' | 'f' => field.width,
+            _               => Some(field.width.unwrap_or(2))
+        };
+        let invalid_field = Error::InvalidField(field.specifier);
+        let (is_negative, n, r) = read_number(rest, &field, max_digits, is_signed).ok_or(invalid_field)?;
+        rest = r;
+
+        if is_year && sign.is_none() {
+            sign = Some(if is_negative { Sign::Negative } else { Sign::Positive });
+        }
+
+        match field.specifier {
+            'h' => h = n,
+            'y' => y = n,
+            'u' => { y = n; epoch = Epoch::Unix },
+            'Y' => full_year = Some(if is_negative { -n } else { n }),
+            'm' => m = n,
+            's' => { m = n; calendar = Calendar::Solar },
+            'd' => d = n,
+            'c' => c = n,
+            'b' => b = n,
+            'f' | 'l' => {},
+            'x' => x = Some(if is_negative { -n } else { n }),
+            _   => unreachable!()
+        }
+        if (field.specifier == 'c' || field.specifier == 'b') && n > 99 {
+            return Err(invalid_field);
+        }
+    }
+    if !rest.is_empty() {
+        return Err(Error::MalformedDate);
+    }
+
+    let (sign, year) = match full_year {
+        Some(year) => (if year < 0 { Sign::Negative } else { Sign::Positive }, year.abs()),
+        None       => (sign.unwrap_or(Sign::Positive), h * 100 + y)
+    };
+
+    let date = Geodate {
+        hectoyear: year / 100,
+        year: year % 100,
+        month: m,
+        day: d,
+        centiday: c,
+        dimiday: b,
+        calendar,
+        epoch,
+        sign,
+        year_start: YearStart::default(),
+        solar_time: SolarTime::default()
+    };
+
+    Ok(ParsedDate { date, timestamp: x })
+}
+
+// Read the sign and the digits of a number at the beginning of a string
+fn read_number<'a>(s: &'a str, field: &Field, max_digits: Option<usize>, is_signed: bool) -> Option<(bool, i64, &'a str)> {
+    let s = match field.padding {
+        Padding::Space => s.trim_start_matches(' '),
+        _              => s
+    };
+    let is_negative = is_signed && s.starts_with('-');
+    let s = if is_negative { &s[1..] } else { s };
+
+    let mut n = s.find(|c: char| !c.is_ascii_digit()).unwrap_or(s.len());
+    if let Some(max) = max_digits {
+        n = n.min(max);
+    }
+    if n == 0 {
+        return None;
+    }
+    let digits = s[..n].parse::<i64>().ok()?;
+
+    Some((is_negative, digits, &s[n..]))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn field(specifier: char, padding: Padding, width: Option<usize>) -> Token {
+        Token::Field(Field { specifier, padding, width })
+    }
+
+    #[test]
+    fn parse_format_test() {
+        assert_eq!(Ok(vec![
+            field('h', Padding::Zero, None),
+            Token::Literal(":".into()),
+            field('y', Padding::Zero, None)
+        ]), parse_format("%h:%y"));
+
+        assert_eq!(Ok(vec![
+            Token::Literal("100%x ".into()),
+            field('d', Padding::None, None),
+            Token::Literal(" ".into()),
+            field('m', Padding::Space, Some(3)),
+            Token::Literal(".".into()),
+            field('f', Padding::Zero, Some(4))
+        ]), parse_format("100%%x %-d %_3m.%4f"));
+
+        assert_eq!(Err(Error::UnknownSpecifier('q')), parse_format("%d %q"));
+        assert_eq!(Err(Error::InvalidFormat), parse_format("%d %"));
+        assert_eq!(Err(Error::InvalidFormat), parse_format("%d %-"));
+    }
+
+    #[test]
+    fn parse_date_test() {
+        let date = parse_date("%h:%y:%m:%d:%c:%b", "01:14:05:24:15:42").unwrap().date;
+        assert_eq!((1, 14, 5, 24, 15, 42), (date.hectoyear, date.year, date.month, date.day, date.centiday, date.dimiday));
+        assert_eq!((Epoch::Gregorian, Calendar::Lunisolar, Sign::Positive), (date.epoch, date.calendar, date.sign));
+
+        let date = parse_date("%u:%s:%d:%c:%b", "-44:02:00:15:42").unwrap().date;
+        assert_eq!(-44, date.signed_year());
+        assert_eq!((Epoch::Unix, Calendar::Solar), (date.epoch, date.calendar));
+
+        let date = parse_date("%h:%u:%m:%d:%c:%b", "-00:00:11:28:99:99").unwrap().date;
+        assert_eq!((Sign::Negative, 0), (date.sign, date.signed_year()));
+
+        let date = parse_date("%Y/%-m/%-d %c%b.%f", "-130/3/7 0542.12345").unwrap().date;
+        assert_eq!((-130, 3, 7, 5, 42), (date.signed_year(), date.month, date.day, date.centiday, date.dimiday));
+
+        let date = parse_date("%y:%m:%d/%l", "14:05:24/13").unwrap().date;
+        assert_eq!((14, 5, 24), (date.year, date.month, date.day));
+
+        let date = parse_date("%y%m%d", "140524").unwrap().date;
+        assert_eq!((14, 5, 24), (date.year, date.month, date.day));
+
+        let date = parse_date("%_d|%_3m", " 4|  7").unwrap().date;
+        assert_eq!((4, 7), (date.day, date.month));
+
+        assert_eq!(Some(-1403322675), parse_date("%x", "-1403322675").unwrap().timestamp);
+        assert_eq!(Some(1403322675), parse_date("time %x", "time 1403322675").unwrap().timestamp);
+
+        assert_eq!(Err(Error::InvalidField('d')), parse_date("%y:%m:%d", "14:05:xx").map(|p| p.date));
+        assert_eq!(Err(Error::InvalidField('c')), parse_date("%d:%3c", "14:100").map(|p| p.date));
+        assert_eq!(Err(Error::MalformedDate), parse_date("%y:%m", "14-05").map(|p| p.date));
+        assert_eq!(Err(Error::MalformedDate), parse_date("%y:%m", "14:05:24").map(|p| p.date));
+    }
+
+    #[test]
+    fn format_number_test() {
+        let f = |padding, width| Field { specifier: 'd', padding, width };
+        assert_eq!("05", format_number(5, &f(Padding::Zero, None), 2));
+        assert_eq!(" 5", format_number(5, &f(Padding::Space, None), 2));
+        assert_eq!("5", format_number(5, &f(Padding::None, None), 2));
+        assert_eq!("0005", format_number(5, &f(Padding::Zero, Some(4)), 2));
+        assert_eq!("-05", format_number(-5, &f(Padding::Zero, None), 2));
+        assert_eq!(" -5", format_number(-5, &f(Padding::Space, Some(3)), 2));
+    }
+
+    #[test]
+    fn format_decimals_test() {
+        assert_eq!("", format_decimals(1, 3, 0));
+        assert_eq!("333", format_decimals(1, 3, 3));
+        assert_eq!("125", format_decimals(9, 8, 3));
+    }
+}
@@ -1,38 +1,410 @@
-use earth_orbit::*;
-use moon_phase::*;
-use sun_transit::*;
+use calendar::*;
+use context::GeodateContext;
+use duration::MetricDuration;
+use error::Error;
+use format::*;
+use iter::try_get_year_with_context;
+use reverse::try_get_timestamp_with_context;
+use timestamp::Timestamp;
 
 use alloc::string::String;
-#[cfg(not(feature = "std"))]
-use num_traits::Float;
-
-#[derive(Clone, Copy)]
+use alloc::string::ToString;
+use alloc::vec::Vec;
+use core::cmp::Ordering;
+use core::fmt;
+use core::str::FromStr;
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+/// Beginning of the count of the years
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub enum Epoch {
+    /// Years counted from 1900
     Gregorian,
-    Unix
+    /// Years counted from 1970
+    Unix,
+    /// Years counted from the year of a timestamp, the year 0 starting with
+    /// the first solstice or equinox starting a year after half a year
+    /// before the timestamp
+    Custom(i64)
 }
 
-#[derive(Clone, Copy)]
+impl Epoch {
+    /// Get an epoch beginning with a year of the gregorian epoch
+    pub fn from_year(year: i64) -> Epoch {
+        let zero = get_epoch_zero(Epoch::Gregorian);
+        Epoch::Custom(zero + (year as f64 * 365.25 * 86400.0) as i64)
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub enum Calendar {
     Lunisolar,
     Solar
 }
 
-static ZEROS: [i64; 6] = [
-               0, // 1970-01-01 | Unix epoch
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum Sign {
+    Negative,
+    Positive
+}
+
+/// Solstice or equinox starting the years of a calendar
+///
+/// The years of a lunisolar calendar start on the day of the first new moon
+/// following the event instead of the day of the event.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum YearStart {
+    #[default]
+    DecemberSolstice,
+    JuneSolstice,
+    MarchEquinox
+}
+
+/// Solar time giving the midnights starting the days of a calendar
+///
+/// The apparent solar time follows the transits of the Sun, while the mean
+/// solar time only depends on the longitude, so that its days all last
+/// exactly 86400 seconds.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum SolarTime {
+    #[default]
+    Apparent,
+    Mean
+}
+
+/// How to handle a month or a day missing from the result of an addition
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Overflow {
+    /// Use the last month of the year or the last day of the month instead
+    Clamp,
+    /// Return no date
+    Reject
+}
+
+const DEFAULT_FORMAT: &str = "%h:%y:%m:%d:%c:%b";
+
+/// A geodate split into its fields
+///
+/// The year is given by its absolute value split into `hectoyear` and `year`,
+/// with the `sign` kept apart because the year before `00:00` is `-00:01`.
+///
+/// With the `serde` feature a geodate is serialized as a map of its fields,
+/// or as its canonical string with `#[serde(with = "geodate::geodate::as_string")]`.
+/// The start of the year and the solar time default to the december solstice
+/// and the apparent solar time when they are missing from the map.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Geodate {
+    pub hectoyear: i64,
+    pub year: i64,
+    pub month: i64,
+    pub day: i64,
+    pub centiday: i64,
+    pub dimiday: i64,
+    pub calendar: Calendar,
+    pub epoch: Epoch,
+    pub sign: Sign,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub year_start: YearStart,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub solar_time: SolarTime
+}
+
+impl Geodate {
+    /// Get the lunisolar geodate of a timestamp with the gregorian epoch
+    pub fn new<T: Timestamp>(timestamp: T, longitude: f64) -> Geodate {
+        Geodate::try_new(timestamp, longitude).unwrap()
+    }
+
+    /// Get the lunisolar geodate of a timestamp with the gregorian epoch
+    ///
+    /// The timestamps are supported from the first new moon after the
+    /// december solstice of the year -2000, starting the year -38:99, to the
+    /// year 3000.
+    ///
+    /// The fraction of a second of a time is dropped, but it is kept in the
+    /// dimidays of the `TryFrom<(OffsetDateTime, f64)>` and
+    /// `TryFrom<(DateTime, f64)>` conversions of the `time` and `chrono`
+    /// features.
+    pub fn try_new<T: Timestamp>(timestamp: T, longitude: f64) -> Result<Geodate, Error> {
+        Geodate::try_new_with_calendar(timestamp, longitude, Epoch::Gregorian, Calendar::Lunisolar)
+    }
+
+    /// Get the geodate of a timestamp with the given epoch and calendar
+    pub fn new_with_calendar<T: Timestamp>(timestamp: T, longitude: f64, epoch: Epoch, calendar: Calendar) -> Geodate {
+        Geodate::try_new_with_calendar(timestamp, longitude, epoch, calendar).unwrap()
+    }
+
+    /// Get the geodate of a timestamp with the given epoch and calendar
+    pub fn try_new_with_calendar<T: Timestamp>(timestamp: T, longitude: f64, epoch: Epoch, calendar: Calendar) -> Result<Geodate, Error> {
+        let mut context = GeodateContext::new(longitude, epoch, calendar);
+        Geodate::try_new_with_context(timestamp, &mut context)
+    }
+
+    /// Get the geodate of a timestamp with the configuration of a context
+    pub fn new_with_context<T: Timestamp>(timestamp: T, context: &mut GeodateContext) -> Geodate {
+        Geodate::try_new_with_context(timestamp, context).unwrap()
+    }
+
+    /// Get the geodate of a timestamp with the configuration of a context
+    ///
+    /// The new moons, solstices, and equinoxes computed for the conversion
+    /// are kept in the context to speed up the next conversions, and the
+    /// fraction of a second of a time is dropped as with `try_new`.
+    pub fn try_new_with_context<T: Timestamp>(timestamp: T, context: &mut GeodateContext) -> Result<Geodate, Error> {
+        let timestamp = timestamp.unix_timestamp();
+        let lon = context.longitude;
+        let epoch = context.epoch;
+        let calendar = context.calendar;
+        let year_start = context.year_start;
+        let solar_time = context.solar_time;
+        let midnight = get_day_start(timestamp, lon, solar_time);
+
+        let (solstice, m, first_day) = match calendar {
+            Calendar::Lunisolar => {
+                // The month starts on the day of the last new moon
+                let (n, new_moon) = try_get_last_new_moon(context, midnight)?;
+
+                // The year starts on the day of the first new moon following
+                // the solstice or the equinox starting the year
+                let solstice = context.try_get_previous_year_start(new_moon + 86400)?;
+                let solstice = get_day_start(solstice, lon, solar_time);
+                let k = try_get_first_lunation(context, solstice)?;
+
+                (solstice, n - k, new_moon)
+            },
+            Calendar::Solar => {
+                // The year starts on the day of the solstice or the equinox
+                // starting the year and the months on the days of the
+                // following equinoxes and solstices
+                let solstice = context.try_get_previous_year_start(midnight + 86400)?;
+                let solstice = get_day_start(solstice, lon, solar_time);
+                let seasons = try_get_month_starts(context, solstice)?;
+                let m = seasons.iter().rposition(|&t| count_days(t, midnight) >= 0).unwrap();
+
+                (solstice, m as i64, seasons[m])
+            }
+        };
+
+        let d = count_days(first_day, midnight);
+        let y = try_get_year_number(context, solstice)?;
+
+        let e = (10000 * (timestamp - midnight)) / 86400;
+
+        Ok(Geodate {
+            hectoyear: y.abs() / 100,
+            year: y.abs() % 100,
+            month: m,
+            day: d,
+            centiday: e / 100,
+            dimiday: e % 100,
+            calendar,
+            epoch,
+            sign: if y < 0 { Sign::Negative } else { Sign::Positive },
+            year_start,
+            solar_time
+        })
+    }
+
+    /// Read a geodate from a string following a format
+    ///
+    /// See `get_formatted_date` for the format. The epoch and the calendar
+    /// of the date are given by the `%u` and `%s` specifiers, and a
+    /// timestamp given with `%x` is ignored.
+    pub fn try_parse(format: &str, date: &str) -> Result<Geodate, Error> {
+        parse_date(format, date).map(|parsed| parsed.date)
+    }
+
+    /// Get the full year with its sign
+    pub fn signed_year(&self) -> i64 {
+        let y = self.hectoyear * 100 + self.year;
+        match self.sign {
+            Sign::Negative => -y,
+            Sign::Positive => y
+        }
+    }
+
+    /// Add a number of days to a geodate at a longitude
+    ///
+    /// The time of the day is kept, and no date is returned for a geodate
+    /// that does not exist or a result out of range.
+    pub fn checked_add_days(&self, days: i64, longitude: f64) -> Option<Geodate> {
+        let mut context = self.get_context(longitude);
+        let midnight = self.try_get_day_start(&mut context).ok()?;
+        let noon = days.checked_mul(86400)?.checked_add(midnight + 43200)?;
+        let date = Geodate::try_new_with_context(noon, &mut context).ok()?;
+
+        Some(Geodate { centiday: self.centiday, dimiday: self.dimiday, ..date })
+    }
+
+    /// Add a number of months to a geodate at a longitude
+    ///
+    /// The day and the time of the day are kept, with a day missing from
+    /// the month of the result handled following the overflow policy.
+    pub fn checked_add_months(&self, months: i64, longitude: f64, overflow: Overflow) -> Option<Geodate> {
+        let mut context = self.get_context(longitude);
+        let midnight = self.try_get_day_start(&mut context).ok()?;
+        match self.calendar {
+            Calendar::Lunisolar => {
+                // Count the months with the lunation numbers of their new moons
+                let (n, _) = try_get_last_new_moon(&mut context, midnight).ok()?;
+                let new_moon = context.try_get_new_moon(n.checked_add(months)?).ok()?;
+                let new_moon = get_day_start(new_moon, longitude, self.solar_time);
+                let month = Geodate::try_new_with_context(new_moon + 43200, &mut context).ok()?;
+                self.with_month(&mut context, month.signed_year(), month.month, overflow)
+            },
+            Calendar::Solar => {
+                // There are always 4 months in a solar year
+                let m = self.month.checked_add(months)?;
+                let year = self.signed_year().checked_add(m.div_euclid(4))?;
+                self.with_month(&mut context, year, m.rem_euclid(4), overflow)
+            }
+        }
+    }
+
+    /// Add a number of years to a geodate at a longitude
+    ///
+    /// The month, the day, and the time of the day are kept, with a month
+    /// missing from the year or a day missing from the month of the result
+    /// handled following the overflow policy.
+    pub fn checked_add_years(&self, years: i64, longitude: f64, overflow: Overflow) -> Option<Geodate> {
+        let mut context = self.get_context(longitude);
+        self.try_get_day_start(&mut context).ok()?;
+        let year = self.signed_year().checked_add(years)?;
+        self.with_month(&mut context, year, self.month, overflow)
+    }
+
+    /// Get the duration elapsed from an earlier geodate at a longitude
+    ///
+    /// The duration is counted between the beginnings of the dimidays of the
+    /// geodates, and is negative if the other geodate is later.
+    pub fn checked_duration_since(&self, earlier: &Geodate, longitude: f64) -> Option<MetricDuration> {
+        let mut context = self.get_context(longitude);
+        let a = self.try_get_day_start(&mut context).ok()?;
+        let mut context = earlier.get_context(longitude);
+        let b = earlier.try_get_day_start(&mut context).ok()?;
+
+        let days = MetricDuration::from_seconds(a - b);
+        let dimidays = (self.centiday - earlier.centiday) * 100 + self.dimiday - earlier.dimiday;
+        Some(days + MetricDuration::from_dimidays(dimidays))
+    }
+
+    // Move a geodate to a month of a year
+    fn with_month(&self, context: &mut GeodateContext, year: i64, month: i64, overflow: Overflow) -> Option<Geodate> {
+        let solstice = try_get_year_solstice(context, year).ok()?;
+        let months = try_get_month_starts(context, solstice).ok()?;
+
+        let last_month = months.len() as i64 - 2;
+        let month = match overflow {
+            _ if month <= last_month => month,
+            Overflow::Clamp => last_month,
+            Overflow::Reject => return None
+        };
+
+        let m = month as usize;
+        let last_day = count_days(months[m], months[m + 1]) - 1;
+        let day = match overflow {
+            _ if self.day <= last_day => self.day,
+            Overflow::Clamp => last_day,
+            Overflow::Reject => return None
+        };
+
+        Some(Geodate {
+            hectoyear: year.abs() / 100,
+            year: year.abs() % 100,
+            month,
+            day,
+            sign: if year < 0 { Sign::Negative } else { Sign::Positive },
+            ..*self
+        })
+    }
+
+    /// Get a context with the configuration of the calendar of a geodate
+    pub fn get_context(&self, longitude: f64) -> GeodateContext {
+        let mut context = GeodateContext::new(longitude, self.epoch, self.calendar);
+        context.year_start = self.year_start;
+        context.solar_time = self.solar_time;
+        context
+    }
+
+    // Get the midnight starting the day of a geodate
+    fn try_get_day_start(&self, context: &mut GeodateContext) -> Result<i64, Error> {
+        let timestamp = try_get_timestamp_with_context(self, context)?;
+        Ok(get_day_start(timestamp, context.longitude, context.solar_time))
+    }
+}
+
+impl fmt::Display for Geodate {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.sign == Sign::Negative {
+            write!(f, "-")?;
+        }
+        write!(f, "{:02}:{:02}:{:02}:{:02}:{:02}:{:02}",
+            self.hectoyear, self.year, self.month, self.day, self.centiday, self.dimiday)
+    }
+}
+
+impl FromStr for Geodate {
+    type Err = Error;
+
+    /// Read a geodate from a string in the default format
+    fn from_str(s: &str) -> Result<Geodate, Error> {
+        Geodate::try_parse(DEFAULT_FORMAT, s)
+    }
+}
+
+impl PartialOrd for Geodate {
+    fn partial_cmp(&self, other: &Geodate) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Geodate {
+    fn cmp(&self, other: &Geodate) -> Ordering {
+        let key = |g: &Geodate| {
+            (g.signed_year(), g.sign, g.month, g.day, g.centiday, g.dimiday, g.epoch, g.calendar, g.year_start, g.solar_time)
+        };
+        key(self).cmp(&key(other))
+    }
+}
 
-      -410227200, // 1957-01-01 | The following dates all
-     -1009843200, // 1938-01-01 | occurs on a new moon day,
-     -2208988800, // 1900-01-01 | but we cannot go further
-     -8551872000, // 1699-01-01 | back than 1620 with the
-    -10950249600  // 1623-01-01 | current delta time formula.
-];
+/// Serialize a geodate as its canonical string
+///
+/// The string is read back with the gregorian epoch and the lunisolar
+/// calendar, like `FromStr`, so an error is returned when serializing a
+/// geodate with another configuration instead of losing it.
+#[cfg(feature = "serde")]
+pub mod as_string {
+    use super::{Geodate, Epoch, Calendar, YearStart, SolarTime};
+    use alloc::string::String;
+    use serde::{de, ser, Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(date: &Geodate, serializer: S) -> Result<S::Ok, S::Error> {
+        let configuration = (date.epoch, date.calendar, date.year_start, date.solar_time);
+        if configuration != (Epoch::Gregorian, Calendar::Lunisolar, YearStart::default(), SolarTime::default()) {
+            return Err(ser::Error::custom("geodate without the gregorian epoch and the lunisolar calendar"));
+        }
+        serializer.collect_str(date)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Geodate, D::Error> {
+        let date = String::deserialize(deserializer)?;
+        date.parse().map_err(de::Error::custom)
+    }
+}
 
 /// Get a string representation of a geodate
 ///
 /// Format:
 /// - %h   Century (hectoyear)
 /// - %y   Solar year starting in 1900, Gregorian friendly epoch
+///   (or with the epoch of a context)
 /// - %m   Lunar month (for a lunisolar calendar)
 /// - %d   Solar day
 /// - %c   Centiday
@@ -41,135 +413,153 @@ static ZEROS: [i64; 6] = [
 /// %u Solar year starting in 1970, Unix friendly epoch
 /// %s Seasonal month (for a solar calendar)
 ///
+/// %Y Full signed year
+/// %f Decimals of the dimiday, with the width giving the precision
+/// %l Number of months in the year, 13 in a lunisolar leap year
 /// %x Unix timestamp
-pub fn get_formatted_date(format: &str, timestamp: i64, longitude: f64) -> String {
-    let mut res = String::from(format);
-    let now = timestamp;
-    let lon = longitude;
+/// %% Literal percent sign
+///
+/// The `%` can be followed by a flag and a width, for example `%_3d`:
+/// - `-`  No padding
+/// - `_`  Padding with spaces
+/// - `0`  Padding with zeros (default)
+pub fn get_formatted_date<T: Timestamp>(format: &str, timestamp: T, longitude: f64) -> String {
+    try_get_formatted_date(format, timestamp, longitude).unwrap()
+}
 
-    if format.contains("%x") {
-        res = res.replace("%x", &format!("{}", now));
+/// Get a string representation of a geodate
+///
+/// See `get_formatted_date` for the format.
+pub fn try_get_formatted_date<T: Timestamp>(format: &str, timestamp: T, longitude: f64) -> Result<String, Error> {
+    try_get_formatted_date_with_year_start(format, timestamp, longitude, YearStart::default())
+}
 
-        if !format.contains("%") {
-            return res;
-        }
-    }
+/// Get a string representation of a geodate with years starting at the
+/// given solstice or equinox
+pub fn get_formatted_date_with_year_start<T: Timestamp>(format: &str, timestamp: T, longitude: f64, year_start: YearStart) -> String {
+    try_get_formatted_date_with_year_start(format, timestamp, longitude, year_start).unwrap()
+}
+
+/// Get a string representation of a geodate with years starting at the
+/// given solstice or equinox
+///
+/// See `get_formatted_date` for the format.
+pub fn try_get_formatted_date_with_year_start<T: Timestamp>(format: &str, timestamp: T, longitude: f64, year_start: YearStart) -> Result<String, Error> {
+    let context = GeodateContext::new(longitude, Epoch::Gregorian, Calendar::Lunisolar);
+    try_get_formatted_date_with_context(format, timestamp, &mut context.with_year_start(year_start))
+}
 
-    let epoch = if format.contains("%u") {
+/// Get a string representation of a geodate with the configuration of a
+/// context
+pub fn get_formatted_date_with_context<T: Timestamp>(format: &str, timestamp: T, context: &mut GeodateContext) -> String {
+    try_get_formatted_date_with_context(format, timestamp, context).unwrap()
+}
+
+/// Get a string representation of a geodate with the configuration of a
+/// context
+///
+/// See `get_formatted_date` for the format. The epoch and the calendar of
+/// the context are used unless the format has the `%u` or `%s` specifiers.
+pub fn try_get_formatted_date_with_context<T: Timestamp>(format: &str, timestamp: T, context: &mut GeodateContext) -> Result<String, Error> {
+    let timestamp = timestamp.unix_timestamp();
+    let longitude = context.longitude;
+    let tokens = parse_format(format)?;
+    let specifiers: Vec<char> = tokens.iter().filter_map(|token| match *token {
+        Token::Field(field) => Some(field.specifier),
+        Token::Literal(_)   => None
+    }).collect();
+
+    let epoch = if specifiers.contains(&'u') {
         Epoch::Unix
     } else {
-        Epoch::Gregorian
+        context.epoch
     };
 
-    let calendar = if format.contains("%s") {
+    let calendar = if specifiers.contains(&'s') {
         Calendar::Solar
     } else {
-        Calendar::Lunisolar
+        context.calendar
     };
 
-    let mut first_new_moon = 0;
-    let mut zero = 0;
-    for &e in &ZEROS {
-        // Pick the nearest zero to shorten calculations
-        first_new_moon = get_next_new_moon(e);
-        zero = get_midnight(first_new_moon, lon);
-        if zero < now {
-            break;
-        }
-    }
-    if now < zero {
-        panic!("too far back in time");
-    }
-
-    let mut new_year = get_next_december_solstice(zero);
-    let mut new_month = match calendar {
-        Calendar::Solar     => get_next_march_equinox(zero),
-        Calendar::Lunisolar => get_next_new_moon(first_new_moon)
+    let mut other_context;
+    let context = if (epoch, calendar) == (context.epoch, context.calendar) {
+        context
+    } else {
+        other_context = GeodateContext::new(longitude, epoch, calendar).with_year_start(context.year_start);
+        other_context.solar_time = context.solar_time;
+        other_context.delta_time_table = context.delta_time_table.clone();
+        &mut other_context
     };
 
-    let mut midnight = get_midnight(now, lon);
-    if midnight > now {
-        midnight -= 86400;
-    } else if midnight <= now - 86400 {
-        midnight += 86400;
-    }
-
-    let mut d = 0;
-    let mut m = 0;
-    let mut y = 0;
-    let mut t = zero;
-    while t < midnight - 2000 { // Mean solar day approximation
-        d += 1;
-        t += 86400;
-        if new_month < t + 86400 {
-            new_month = match calendar {
-                Calendar::Solar => {
-                    match m {
-                        0 => get_next_june_solstice(new_month),
-                        1 => get_next_september_equinox(new_month),
-                        2 => get_next_december_solstice(new_month),
-                        3 => get_next_march_equinox(new_month),
-                        _ => unreachable!()
-                    }
-                },
-                Calendar::Lunisolar => {
-                    get_next_new_moon(new_month)
-                }
-            };
-            d = 0;
-            m += 1;
-            if new_year < t + 86400 {
-                new_year = get_next_december_solstice(new_year);
-                m = 0;
-                y += 1;
-            }
-        }
-    }
-
-    let epoch_zero = match epoch {
-        Epoch::Unix      => ZEROS[0],
-        Epoch::Gregorian => ZEROS[3]
+    // Skip the calculation of the geodate with a machine format
+    let date = if specifiers.iter().all(|&c| c == 'x') {
+        None
+    } else {
+        Some(Geodate::try_new_with_context(timestamp, context)?)
     };
 
-    y += ((zero - epoch_zero) as f64 / 86400.0 / 365.25).round() as i64;
-
-    if y < 0 {
-        y = y.abs();
-        if res.contains("%h") || res.contains("%y") || res.contains("%u") {
-            res.insert(0, '-');
+    // The sign is printed before the first field of the year unless the
+    // full signed year is given
+    let mut is_signed = specifiers.contains(&'Y');
+
+    let mut res = String::new();
+    for token in &tokens {
+        let field = match *token {
+            Token::Literal(ref literal) => {
+                res.push_str(literal);
+                continue;
+            },
+            Token::Field(field) => field
+        };
+        if field.specifier == 'x' {
+            res.push_str(&format_number(timestamp, &field, 0));
+            continue;
         }
-    }
 
-    if res.contains("%h") {
-        let h = y / 100;
-        res = res.replace("%h", &format!("{:02}", h));
+        let date = date.unwrap();
+        if let 'h' | 'y' | 'u' = field.specifier {
+            if !is_signed && date.sign == Sign::Negative {
+                res.push('-');
+            }
+            is_signed = true;
+        }
+        let n = match field.specifier {
+            'h'       => date.hectoyear,
+            'y' | 'u' => date.year,
+            'Y'       => date.signed_year(),
+            'm' | 's' => date.month,
+            'd'       => date.day,
+            'c'       => date.centiday,
+            'b'       => date.dimiday,
+            'l'       => try_get_year_with_context(date.signed_year(), context)?.months,
+            'f'       => {
+                let seconds = timestamp - get_day_start(timestamp, longitude, context.solar_time);
+                let precision = field.width.unwrap_or(2);
+                res.push_str(&format_decimals(10000 * seconds, 86400, precision));
+                continue;
+            },
+            _ => unreachable!()
+        };
+        res.push_str(&format_number(n, &field, 2));
     }
-    y = y % 100;
-
-    res = res.replace("%u", &format!("{:02}", y));
-    res = res.replace("%y", &format!("{:02}", y));
 
-    res = res.replace("%m", &format!("{:02}", m));
-    res = res.replace("%s", &format!("{:02}", m));
-    res = res.replace("%d", &format!("{:02}", d));
-
-    let e = (10000 * (now - midnight)) / 86400;
-    let c = e / 100;
-    let b = e % 100;
-    res = res.replace("%c", &format!("{:02}", c));
-    res = res.replace("%b", &format!("{:02}", b));
+    Ok(res)
+}
 
-    res
+/// Get date with the default formatting
+pub fn get_date<T: Timestamp>(timestamp: T, longitude: f64) -> String {
+    Geodate::new(timestamp, longitude).to_string()
 }
 
 /// Get date with the default formatting
-pub fn get_date(timestamp: i64, longitude: f64) -> String {
-    get_formatted_date("%h:%y:%m:%d:%c:%b", timestamp, longitude)
+pub fn try_get_date<T: Timestamp>(timestamp: T, longitude: f64) -> Result<String, Error> {
+    Geodate::try_new(timestamp, longitude).map(|date| date.to_string())
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use alloc::collections::BTreeSet;
     use utils::*;
 
     #[test]
@@ -177,6 +567,251 @@ mod tests {
         assert_eq!("01:14:05:24:15:42", get_date(1403322675, -1.826189));
     }
 
+    #[test]
+    fn geodate_test() {
+        let date = Geodate::new(1403322675, -1.826189);
+        assert_eq!(1, date.hectoyear);
+        assert_eq!(14, date.year);
+        assert_eq!(5, date.month);
+        assert_eq!(24, date.day);
+        assert_eq!(15, date.centiday);
+        assert_eq!(42, date.dimiday);
+        assert_eq!(Sign::Positive, date.sign);
+        assert_eq!("01:14:05:24:15:42", date.to_string());
+
+        let date = Geodate::new_with_calendar(0, 0.0, Epoch::Unix, Calendar::Lunisolar);
+        assert_eq!(Sign::Negative, date.sign);
+        assert_eq!(-1, date.signed_year());
+        assert_eq!("-00:01:11:22:99:75", date.to_string());
+    }
+
+    #[test]
+    fn geodate_with_context_test() {
+        let mut context = GeodateContext::new(0.0, Epoch::Unix, Calendar::Lunisolar);
+        let times = vec![
+            parse_time("1970-12-28T00:01:20+00:00"),
+            parse_time("1970-12-28T00:01:30+00:00"),
+            parse_time("2014-01-01T00:03:20+00:00"),
+            parse_time("2014-01-01T00:03:40+00:00"),
+            parse_time("1900-01-01T00:03:40+00:00"),
+            parse_time("2020-09-15T23:55:01+00:00")
+        ];
+        for &t in &times {
+            let date = Geodate::new_with_context(t, &mut context);
+            assert_eq!(Geodate::new_with_calendar(t, 0.0, Epoch::Unix, Calendar::Lunisolar), date);
+        }
+    }
+
+    #[test]
+    fn geodate_with_mean_solar_time_test() {
+        // The mean midnight is at 00:00 UTC on the prime meridian and six
+        // hours earlier at 90° east
+        let context = GeodateContext::new(0.0, Epoch::Unix, Calendar::Lunisolar);
+        let mut context = context.with_solar_time(SolarTime::Mean);
+        let t = parse_time("2014-06-21T03:51:15+00:00");
+        let date = Geodate::new_with_context(t, &mut context);
+        assert_eq!(SolarTime::Mean, date.solar_time);
+        assert_eq!((16, 5), (date.centiday, date.dimiday));
+        assert_eq!(Ok(parse_time("2014-06-21T00:00:00+00:00")), date.try_get_day_start(&mut context));
+        assert_eq!("16:05", get_formatted_date_with_context("%c:%b", t, &mut context));
+        assert_eq!("16:05:90", get_formatted_date_with_context("%c:%b:%f", t, &mut context));
+
+        let context = GeodateContext::new(90.0, Epoch::Unix, Calendar::Lunisolar);
+        let mut context = context.with_solar_time(SolarTime::Mean);
+        let date = Geodate::new_with_context(t, &mut context);
+        assert_eq!((41, 5), (date.centiday, date.dimiday));
+        assert_eq!(Ok(parse_time("2014-06-20T18:00:00+00:00")), date.try_get_day_start(&mut context));
+
+        // The day is the same as with the apparent solar time away from
+        // the midnights
+        let mut apparent = GeodateContext::new(90.0, Epoch::Unix, Calendar::Lunisolar);
+        let other = Geodate::new_with_context(t, &mut apparent);
+        assert_eq!((other.month, other.day), (date.month, date.day));
+        assert_ne!(other.centiday, date.centiday);
+    }
+
+    #[test]
+    fn geodate_from_str_test() {
+        let date = Geodate::new(1403322675, -1.826189);
+        assert_eq!(Ok(date), "01:14:05:24:15:42".parse::<Geodate>());
+        assert_eq!(Ok(date), Geodate::try_parse("%Y-%m-%d %c:%b", "114-05-24 15:42"));
+
+        let date = Geodate::new(parse_time("1900-01-01T00:03:30+00:00"), 0.0);
+        assert_eq!(Ok(date), date.to_string().parse::<Geodate>());
+        assert_eq!(Err(Error::InvalidField('m')), "01:14:xx:24:15:42".parse::<Geodate>());
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn geodate_serde_test() {
+        let date = Geodate::new(1403322675, -1.826189);
+        let json = r#"{"hectoyear":1,"year":14,"month":5,"day":24,"centiday":15,"dimiday":42,"calendar":"Lunisolar","epoch":"Gregorian","sign":"Positive","year_start":"DecemberSolstice","solar_time":"Apparent"}"#;
+        assert_eq!(json, serde_json::to_string(&date).unwrap());
+        assert_eq!(date, serde_json::from_str(json).unwrap());
+
+        // The start of the year and the solar time are optional
+        let json = r#"{"hectoyear":1,"year":14,"month":5,"day":24,"centiday":15,"dimiday":42,"calendar":"Lunisolar","epoch":"Gregorian","sign":"Positive"}"#;
+        assert_eq!(date, serde_json::from_str(json).unwrap());
+
+        #[derive(Debug, PartialEq, Serialize, Deserialize)]
+        struct Log {
+            #[serde(with = "as_string")]
+            date: Geodate
+        }
+        let log = Log { date };
+        let json = r#"{"date":"01:14:05:24:15:42"}"#;
+        assert_eq!(json, serde_json::to_string(&log).unwrap());
+        assert_eq!(log, serde_json::from_str(json).unwrap());
+        assert!(serde_json::from_str::<Log>(r#"{"date":"01:14"}"#).is_err());
+
+        // The string cannot keep another epoch or calendar
+        let date = Geodate::new_with_calendar(1403322675, -1.826189, Epoch::Unix, Calendar::Solar);
+        assert_eq!(date, serde_json::from_str(&serde_json::to_string(&date).unwrap()).unwrap());
+        assert!(serde_json::to_string(&Log { date }).is_err());
+        let date = Geodate { solar_time: SolarTime::Mean, ..log.date };
+        assert!(serde_json::to_string(&Log { date }).is_err());
+    }
+
+    #[test]
+    fn checked_add_days_test() {
+        let parse = |date| Geodate::try_parse("%u:%m:%d:%c:%b", date).unwrap();
+
+        // The first month of the year 00 has 30 days
+        let date = parse("00:00:29:50:00");
+        assert_eq!(Some(parse("00:01:00:50:00")), date.checked_add_days(1, 0.0));
+        assert_eq!(Some(parse("00:00:00:50:00")), date.checked_add_days(-29, 0.0));
+        assert_eq!(Some(parse("-01:11:28:50:00")), date.checked_add_days(-30, 0.0));
+        assert_eq!(Some(date), date.checked_add_days(365, 0.0).unwrap().checked_add_days(-365, 0.0));
+
+        assert_eq!(None, parse("00:01:29:00:00").checked_add_days(1, 0.0));
+        assert_eq!(None, date.checked_add_days(i64::MAX, 0.0));
+    }
+
+    #[test]
+    fn checked_add_months_test() {
+        let parse = |date| Geodate::try_parse("%u:%m:%d:%c:%b", date).unwrap();
+
+        // The second month of the year 00 has 29 days
+        let date = parse("00:00:29:50:00");
+        assert_eq!(Some(parse("00:01:28:50:00")), date.checked_add_months(1, 0.0, Overflow::Clamp));
+        assert_eq!(None, date.checked_add_months(1, 0.0, Overflow::Reject));
+        assert_eq!(Some(parse("00:02:29:50:00")), date.checked_add_months(2, 0.0, Overflow::Reject));
+
+        // The year 00 has 12 months and the year -01 too
+        assert_eq!(Some(parse("01:00:28:50:00")), date.checked_add_months(12, 0.0, Overflow::Clamp));
+        assert_eq!(Some(parse("-01:11:10:00:00")), parse("00:00:10:00:00").checked_add_months(-1, 0.0, Overflow::Reject));
+
+        let parse = |date| Geodate::try_parse("%u:%s:%d:%c:%b", date).unwrap();
+        let date = parse("00:03:10:00:00");
+        assert_eq!(Some(parse("01:01:10:00:00")), date.checked_add_months(2, 0.0, Overflow::Reject));
+        assert_eq!(Some(parse("-01:03:10:00:00")), date.checked_add_months(-4, 0.0, Overflow::Reject));
+    }
+
+    #[test]
+    fn checked_add_years_test() {
+        let parse = |date| Geodate::try_parse("%u:%m:%d:%c:%b", date).unwrap();
+
+        // The year 01 has 13 months and the year 02 has 12 months
+        let date = parse("01:12:05:00:00");
+        assert_eq!(Some(parse("02:11:05:00:00")), date.checked_add_years(1, 0.0, Overflow::Clamp));
+        assert_eq!(None, date.checked_add_years(1, 0.0, Overflow::Reject));
+        assert_eq!(Some(parse("-01:11:05:00:00")), date.checked_add_years(-2, 0.0, Overflow::Clamp));
+
+        // The third month of the solar year 00 has 94 days and 93 in 01
+        let parse = |date| Geodate::try_parse("%u:%s:%d:%c:%b", date).unwrap();
+        let date = parse("00:02:93:00:00");
+        assert_eq!(Some(parse("01:02:92:00:00")), date.checked_add_years(1, 0.0, Overflow::Clamp));
+        assert_eq!(None, date.checked_add_years(1, 0.0, Overflow::Reject));
+        assert_eq!(None, date.checked_add_years(5000, 0.0, Overflow::Clamp));
+    }
+
+    #[test]
+    fn checked_duration_since_test() {
+        let parse = |date| Geodate::try_parse("%u:%m:%d:%c:%b", date).unwrap();
+
+        let a = parse("00:00:29:50:00");
+        let b = parse("00:00:29:10:05");
+        assert_eq!(Some(MetricDuration::from_dimidays(3995)), a.checked_duration_since(&b, 0.0));
+        assert_eq!(Some(MetricDuration::from_dimidays(-3995)), b.checked_duration_since(&a, 0.0));
+
+        // The days are measured between midnights, which are not exactly
+        // a mean solar day apart
+        let c = a.checked_add_days(3, 0.0).unwrap();
+        let duration = c.checked_duration_since(&a, 0.0).unwrap();
+        assert_eq!(3, duration.days());
+        assert!((duration - MetricDuration::from_days(3)).abs() < MetricDuration::from_dimidays(10));
+        assert_eq!(None, parse("00:01:29:00:00").checked_duration_since(&a, 0.0));
+    }
+
+    #[test]
+    fn geodate_ord_test() {
+        let a = Geodate::new_with_calendar(0, 0.0, Epoch::Unix, Calendar::Lunisolar);
+        let b = Geodate::new_with_calendar(86400, 0.0, Epoch::Unix, Calendar::Lunisolar);
+        let c = Geodate::new_with_calendar(parse_time("1970-01-07T00:06:15+00:00"), 0.0, Epoch::Unix, Calendar::Lunisolar);
+        let d = Geodate::new_with_calendar(parse_time("1970-12-28T00:01:30+00:00"), 0.0, Epoch::Unix, Calendar::Lunisolar);
+        assert!(a < b);
+        assert!(b < c);
+        assert!(c < d);
+        assert_eq!(a, Geodate::new_with_calendar(0, 0.0, Epoch::Unix, Calendar::Lunisolar));
+
+        // Geodates differing only by the start of their years or their days
+        // are not equal
+        let e = Geodate { year_start: YearStart::JuneSolstice, ..a };
+        assert_ne!(Ordering::Equal, a.cmp(&e));
+        let f = Geodate { solar_time: SolarTime::Mean, ..a };
+        assert_ne!(Ordering::Equal, a.cmp(&f));
+        let dates: BTreeSet<Geodate> = vec![a, e, f].into_iter().collect();
+        assert_eq!(3, dates.len());
+    }
+
+    #[test]
+    fn try_get_date_test() {
+        assert_eq!(Ok("01:14:05:24:15:42".to_string()), try_get_date(1403322675, -1.826189));
+        assert_eq!(Err(Error::OutOfRange), try_get_date(parse_time("3500-01-01T00:00:00+00:00"), 0.0));
+
+        // The first lunisolar year starts at the first new moon after the
+        // first december solstice in the range of ΔT
+        assert_eq!(Ok("-38:99:00:00:00:00".to_string()), try_get_date(-125249759342, 0.0));
+        assert_eq!(Err(Error::OutOfRange), try_get_date(-125249759343, 0.0));
+        assert_eq!(Ok("-38:98:00:00:00:00".to_string()), try_get_date(-125216667791, 0.0));
+        let date = Geodate::try_new_with_calendar(-125250623609, 0.0, Epoch::Gregorian, Calendar::Solar);
+        assert_eq!("-38:99:00:00:00:00", date.unwrap().to_string());
+        let date = Geodate::try_new_with_calendar(-125250623610, 0.0, Epoch::Gregorian, Calendar::Solar);
+        assert_eq!(Err(Error::OutOfRange), date);
+        assert_eq!(Ok("-20000000000".to_string()), try_get_formatted_date("%x", -20000000000, 0.0));
+    }
+
+    #[test]
+    fn get_ancient_date_test() {
+        let format = "%h:%y:%m:%d";
+        assert_eq!("-37:33:04:17", get_formatted_date(format, -120000000000, 0.0));
+        assert_eq!("-19:00:12:19", get_formatted_date(format, parse_time("0001-01-01T00:00:00+00:00"), 0.0));
+        assert_eq!("-08:34:09:16", get_formatted_date(format, parse_time("1066-10-14T09:00:00+00:00"), 0.0));
+        assert_eq!("-03:01:12:14", get_formatted_date(format, parse_time("1600-01-01T00:00:00+00:00"), 0.0));
+    }
+
+    #[test]
+    fn get_custom_date_test() {
+        let t = 1403322675;
+        let lon = -1.826189;
+        assert_eq!("114-05-24", get_formatted_date("%Y-%m-%d", t, lon));
+        assert_eq!("114 5 24", get_formatted_date("%Y %-m %-d", t, lon));
+        assert_eq!("1:14: 5:24", get_formatted_date("%-h:%y:%_m:%d", t, lon));
+        assert_eq!("001:014", get_formatted_date("%3h:%3y", t, lon));
+        assert_eq!("15:42.8", get_formatted_date("%c:%b.%1f", t, lon));
+        assert_eq!("15:42.82407", get_formatted_date("%c:%b.%5f", t, lon));
+        assert_eq!("100% 24", get_formatted_date("100%% %d", t, lon));
+        assert_eq!("%x 24", get_formatted_date("%%x %d", t, lon));
+        assert_eq!("time: 1403322675", get_formatted_date("time: %x", t, lon));
+        assert_eq!("-01 01", get_formatted_date("%Y %u", 0, 0.0));
+        assert_eq!("year -01", get_formatted_date("year %u", 0, 0.0));
+        assert_eq!("01/13", get_formatted_date("%u/%l", 44000000, 0.0));
+        assert_eq!("00/12", get_formatted_date("%u/%l", 1000000, 0.0));
+        assert_eq!("01/04", get_formatted_date("%s/%l", 44000000, 0.0));
+        assert_eq!(Err(Error::UnknownSpecifier('q')), try_get_formatted_date("%q", t, lon));
+        assert_eq!(Err(Error::InvalidFormat), try_get_formatted_date("%d%", t, lon));
+    }
+
     #[test]
     fn get_solar_date_test() {
         let format = "%u:%s:%d:%c:%b";
@@ -251,5 +886,13 @@ mod tests {
         assert_eq!("50:08:27:99:99", get_formatted_date(format, parse_time("2020-09-15T23:55:01+00:00"), 0.0));
         assert_eq!("50:08:28:00:00", get_formatted_date(format, parse_time("2020-09-15T23:55:02+00:00"), 0.0));
         assert_eq!("50:08:28:00:00", get_formatted_date(format, parse_time("2020-09-15T23:55:03+00:00"), 0.0));
+
+        // New moon a few minutes after midnight
+        assert_eq!("89:08:29:32:41", get_formatted_date(format, 9142350577, 28.0));
+        assert_eq!("89:09:00:00:30", get_formatted_date(format, 9142409233, 28.0));
+
+        // New moon the day before the december solstice
+        assert_eq!("22:00:00:36:94", get_formatted_date(format, 10160428403, -66.0));
+        assert_eq!("22:01:03:71:07", get_formatted_date(format, 10163309912, -66.0));
     }
 }
@@ -0,0 +1,334 @@
+use geodate::*;
+use calendar::*;
+use context::GeodateContext;
+use error::Error;
+use timestamp::Timestamp;
+
+use alloc::vec::Vec;
+use core::ops::Range;
+
+/// A day of a calendar at a longitude
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Day {
+    /// Midnight starting the day
+    pub start: i64,
+    /// Midnight starting the next day
+    pub end: i64,
+    /// Geodate at the beginning of the day
+    pub date: Geodate
+}
+
+/// A month of a calendar at a longitude
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Month {
+    /// Midnight starting the first day of the month
+    pub start: i64,
+    /// Midnight starting the first day of the next month
+    pub end: i64,
+    /// Geodate at the beginning of the month
+    pub date: Geodate,
+    /// Number of days in the month
+    pub days: i64
+}
+
+/// A year of a calendar at a longitude
+///
+/// A lunisolar year has 12 months, or 13 months in a leap year, while a
+/// solar year always has 4 seasonal months.
+///
+/// The months of a lunisolar year are counted from the first new moon after
+/// the solstice or the equinox starting the year, so the month added to a
+/// leap year is always the last one, the 13th month numbered 12, and not a
+/// month chosen by its solar terms as in other lunisolar calendars.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Year {
+    /// Midnight starting the first day of the year
+    pub start: i64,
+    /// Midnight starting the first day of the next year
+    pub end: i64,
+    /// Geodate at the beginning of the year
+    pub date: Geodate,
+    /// Number of months in the year
+    pub months: i64,
+    /// Number of days in the year
+    pub days: i64,
+    /// Midnights starting the first day of each month
+    pub month_starts: Vec<i64>
+}
+
+impl Year {
+    /// Check if a lunisolar year has a 13th month
+    pub fn is_leap(&self) -> bool {
+        self.thirteenth_month().is_some()
+    }
+
+    /// Get the number of the 13th month added at the end of a lunisolar leap
+    /// year
+    pub fn thirteenth_month(&self) -> Option<i64> {
+        match self.date.calendar {
+            Calendar::Lunisolar if self.months > 12 => Some(12),
+            _ => None
+        }
+    }
+}
+
+/// An iterator over the days of a calendar, see `days_between`
+#[derive(Clone, Debug)]
+pub struct Days {
+    context: GeodateContext,
+    midnight: i64,
+    end: i64
+}
+
+/// An iterator over the months of a year, see `months_in_year`
+#[derive(Clone, Debug)]
+pub struct Months {
+    context: GeodateContext,
+    year: i64,
+    starts: Option<Vec<i64>>,
+    index: usize
+}
+
+/// An iterator over the years of a calendar, see `years`
+#[derive(Clone, Debug)]
+pub struct Years {
+    context: GeodateContext,
+    range: Range<i64>
+}
+
+/// Get a lunisolar year with the gregorian epoch
+pub fn get_year(year: i64, longitude: f64) -> Year {
+    try_get_year(year, longitude).unwrap()
+}
+
+/// Get a lunisolar year with the gregorian epoch
+pub fn try_get_year(year: i64, longitude: f64) -> Result<Year, Error> {
+    let mut context = GeodateContext::new(longitude, Epoch::Gregorian, Calendar::Lunisolar);
+    try_get_year_with_context(year, &mut context)
+}
+
+/// Get a year with the configuration of a context
+pub fn try_get_year_with_context(year: i64, context: &mut GeodateContext) -> Result<Year, Error> {
+    let solstice = try_get_year_solstice(context, year)?;
+    let month_starts = try_get_month_starts(context, solstice)?;
+    let n = month_starts.len() - 1;
+    let (start, end) = (month_starts[0], month_starts[n]);
+
+    Ok(Year {
+        start,
+        end,
+        date: get_date(context, year, 0, 0),
+        months: n as i64,
+        days: count_days(start, end),
+        month_starts: month_starts[..n].to_vec()
+    })
+}
+
+/// Iterate over the lunisolar days with the gregorian epoch between two times
+///
+/// The day of the start is the first day, and the last day is the day
+/// beginning before the end.
+pub fn days_between<T: Timestamp>(start: T, end: T, longitude: f64) -> Days {
+    let context = GeodateContext::new(longitude, Epoch::Gregorian, Calendar::Lunisolar);
+    days_between_with_context(start, end, context)
+}
+
+/// Iterate over the days between two times with the configuration of a context
+pub fn days_between_with_context<T: Timestamp>(start: T, end: T, context: GeodateContext) -> Days {
+    let midnight = get_day_start(start.unix_timestamp(), context.longitude, context.solar_time);
+    Days { context, midnight, end: end.unix_timestamp() }
+}
+
+/// Iterate over the lunisolar months of a year with the gregorian epoch
+pub fn months_in_year(year: i64, longitude: f64) -> Months {
+    let context = GeodateContext::new(longitude, Epoch::Gregorian, Calendar::Lunisolar);
+    months_in_year_with_context(year, context)
+}
+
+/// Iterate over the months of a year with the configuration of a context
+pub fn months_in_year_with_context(year: i64, context: GeodateContext) -> Months {
+    Months { context, year, starts: None, index: 0 }
+}
+
+/// Iterate over a range of lunisolar years with the gregorian epoch
+pub fn years(range: Range<i64>, longitude: f64) -> Years {
+    let context = GeodateContext::new(longitude, Epoch::Gregorian, Calendar::Lunisolar);
+    years_with_context(range, context)
+}
+
+/// Iterate over a range of years with the configuration of a context
+pub fn years_with_context(range: Range<i64>, context: GeodateContext) -> Years {
+    Years { context, range }
+}
+
+// Get the geodate at the beginning of a day
+fn get_date(context: &GeodateContext, year: i64, month: i64, day: i64) -> Geodate {
+    Geodate {
+        hectoyear: year.abs() / 100,
+        year: year.abs() % 100,
+        month,
+        day,
+        centiday: 0,
+        dimiday: 0,
+        calendar: context.calendar,
+        epoch: context.epoch,
+        sign: if year < 0 { Sign::Negative } else { Sign::Positive },
+        year_start: context.year_start,
+        solar_time: context.solar_time
+    }
+}
+
+impl Iterator for Days {
+    type Item = Result<Day, Error>;
+
+    fn next(&mut self) -> Option<Result<Day, Error>> {
+        if self.midnight >= self.end {
+            return None;
+        }
+        let (lon, st) = (self.context.longitude, self.context.solar_time);
+        let start = self.midnight;
+        let end = get_day_start(start + 86400 + 43200, lon, st);
+        self.midnight = end;
+
+        let date = Geodate::try_new_with_context(start + 43200, &mut self.context);
+        Some(date.map(|date| {
+            Day { start, end, date: Geodate { centiday: 0, dimiday: 0, ..date } }
+        }))
+    }
+}
+
+impl Iterator for Months {
+    type Item = Result<Month, Error>;
+
+    fn next(&mut self) -> Option<Result<Month, Error>> {
+        if self.starts.is_none() {
+            let starts = try_get_year_solstice(&mut self.context, self.year).and_then(|solstice| {
+                try_get_month_starts(&mut self.context, solstice)
+            });
+            match starts {
+                Ok(starts) => self.starts = Some(starts),
+                Err(e) => {
+                    self.starts = Some(Vec::new());
+                    return Some(Err(e));
+                }
+            }
+        }
+
+        let starts = self.starts.as_ref().unwrap();
+        let m = self.index;
+        if m + 1 >= starts.len() {
+            return None;
+        }
+        self.index += 1;
+
+        let (start, end) = (starts[m], starts[m + 1]);
+        let date = get_date(&self.context, self.year, m as i64, 0);
+        Some(Ok(Month { start, end, date, days: count_days(start, end) }))
+    }
+}
+
+impl Iterator for Years {
+    type Item = Result<Year, Error>;
+
+    fn next(&mut self) -> Option<Result<Year, Error>> {
+        let year = self.range.next()?;
+        Some(try_get_year_with_context(year, &mut self.context))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.range.size_hint()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use utils::*;
+    use alloc::string::ToString;
+
+    #[test]
+    fn days_between_test() {
+        let start = parse_time("2014-06-20T12:00:00+00:00");
+        let end = parse_time("2014-06-23T00:00:00+00:00");
+        let days: Vec<Day> = days_between(start, end, -1.826189).map(Result::unwrap).collect();
+        assert_eq!(3, days.len());
+        assert_eq!("01:14:05:23:00:00", days[0].date.to_string());
+        assert_eq!("01:14:05:24:00:00", days[1].date.to_string());
+        for day in days.iter() {
+            assert_eq!(day.date, Geodate::new(day.start, -1.826189));
+            assert_eq!(day.date.day + 1, Geodate::new(day.end, -1.826189).day);
+        }
+        for i in 1..days.len() {
+            assert_eq!(days[i - 1].end, days[i].start);
+        }
+
+        assert_eq!(0, days_between(end, start, -1.826189).count());
+    }
+
+    #[test]
+    fn months_in_year_test() {
+        // The year 00 of the unix epoch has 12 months
+        let context = GeodateContext::new(0.0, Epoch::Unix, Calendar::Lunisolar);
+        let months: Vec<Month> = months_in_year_with_context(0, context).map(Result::unwrap).collect();
+        let days: Vec<i64> = months.iter().map(|month| month.days).collect();
+        assert_eq!(vec![30, 29, 30, 29, 30, 29, 30, 29, 30, 30, 29, 30], days);
+        assert_eq!(parse_time("1970-01-07T00:06:14+00:00"), months[0].start);
+        assert_eq!(parse_time("1970-12-28T00:01:28+00:00"), months[11].end);
+        assert_eq!((0, 11, 0), (months[11].date.signed_year(), months[11].date.month, months[11].date.day));
+
+        // The solar calendar has 4 seasonal months
+        let context = GeodateContext::new(0.0, Epoch::Unix, Calendar::Solar);
+        assert_eq!(4, months_in_year_with_context(0, context).count());
+
+        let mut months = months_in_year(5000, 0.0);
+        assert_eq!(Some(Err(Error::OutOfRange)), months.next());
+        assert_eq!(None, months.next());
+    }
+
+    #[test]
+    fn get_year_test() {
+        // The year 01 of the unix epoch is a leap year
+        let mut context = GeodateContext::new(0.0, Epoch::Unix, Calendar::Lunisolar);
+        let year = try_get_year_with_context(1, &mut context).unwrap();
+        assert_eq!((13, 384), (year.months, year.days));
+        assert_eq!(13, year.month_starts.len());
+        assert_eq!(year.start, year.month_starts[0]);
+        assert!(year.is_leap());
+        assert_eq!(Some(12), year.thirteenth_month());
+        let months: Vec<Month> = months_in_year_with_context(1, context.clone()).map(Result::unwrap).collect();
+        assert_eq!(months.iter().map(|month| month.start).collect::<Vec<i64>>(), year.month_starts);
+
+        let year = try_get_year_with_context(2, &mut context).unwrap();
+        assert!(!year.is_leap());
+        assert_eq!(None, year.thirteenth_month());
+
+        // The solar calendar has no leap years
+        let mut context = GeodateContext::new(0.0, Epoch::Unix, Calendar::Solar);
+        let year = try_get_year_with_context(1, &mut context).unwrap();
+        assert_eq!((4, 365), (year.months, year.days));
+        assert!(!year.is_leap());
+
+        assert_eq!(Ok(get_year(114, -1.826189)), try_get_year(114, -1.826189));
+        assert_eq!(Err(Error::OutOfRange), try_get_year(1100, 0.0));
+    }
+
+    #[test]
+    fn years_test() {
+        let context = GeodateContext::new(0.0, Epoch::Unix, Calendar::Lunisolar);
+        let res: Vec<Year> = years_with_context(-1..3, context).map(Result::unwrap).collect();
+        let months: Vec<i64> = res.iter().map(|year| year.months).collect();
+        assert_eq!(vec![12, 12, 13, 12], months);
+        let days: Vec<i64> = res.iter().map(|year| year.days).collect();
+        assert_eq!(vec![354, 355, 384, 354], days);
+        assert_eq!(vec![-1, 0, 1, 2], res.iter().map(|year| year.date.signed_year()).collect::<Vec<i64>>());
+        for i in 1..res.len() {
+            assert_eq!(res[i - 1].end, res[i].start);
+        }
+
+        // The year 1100 of the gregorian epoch ends after the year 3000
+        let res: Vec<Result<Year, Error>> = years(1098..1101, 0.0).collect();
+        assert_eq!(3, res.len());
+        assert!(res[1].is_ok());
+        assert_eq!(Err(Error::OutOfRange), res[2]);
+    }
+}
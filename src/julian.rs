@@ -1,6 +1,7 @@
 #[cfg(not(feature = "std"))]
 use num_traits::Float;
 
+#[allow(clippy::inconsistent_digit_grouping)]
 pub const J2000: f64 = 2451_545.0; // TODO: Add 0.0009 to this value?
 
 pub fn unix_to_julian(timestamp: i64) -> f64 {
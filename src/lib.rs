@@ -48,15 +48,30 @@ extern crate std;
 #[macro_use]
 extern crate alloc;
 extern crate num_traits;
+#[cfg(feature = "serde")]
+extern crate serde;
+#[cfg(feature = "time")]
+extern crate time;
+#[cfg(feature = "chrono")]
+extern crate chrono;
 
 #[macro_use]
 mod utils;
 
+mod calendar;
+mod coordinates;
+mod format;
 mod julian;
 mod math;
 
 pub mod delta_time;
 
+/// Errors returned by the fallible functions
+pub mod error;
+
+/// Expresses spans of time in metric units
+pub mod duration;
+
 /// Computes solstices and equinoxes times
 pub mod earth_orbit;
 
@@ -66,14 +81,32 @@ pub mod geodate;
 /// Computes phases of the Moon and lunation numbers
 pub mod moon_phase;
 
+/// Describes the place of an observer on the Earth
+pub mod observer;
+
+/// Computes the position of the Moon in the sky
+pub mod moon_position;
+
 /// Computes moonrise and moonset times
 pub mod moon_transit;
 
 /// Computes sunrise, sunset, midnight, and midday times
 pub mod sun_transit;
 
+/// Computes the position of the Sun in the sky
+pub mod sun_position;
+
 /// Computes ephemeris
 pub mod ephemeris;
 
+/// Caches the events of a calendar for repeated conversions
+pub mod context;
+
+/// Accepts points in time from other crates
+pub mod timestamp;
+
+/// Iterates over the days, months, and years of a calendar
+pub mod iter;
+
 /// Reverse a geodate into a timestamp
 pub mod reverse;
@@ -4,10 +4,20 @@ extern crate geodate;
 use getopts::Options;
 
 use geodate::geodate::*;
+use geodate::context::GeodateContext;
+use geodate::duration::MetricDuration;
+use geodate::earth_orbit::*;
 use geodate::ephemeris::*;
+use geodate::iter::*;
+use geodate::moon_phase::*;
+use geodate::observer::Observer;
 use geodate::reverse::*;
+use geodate::error::Error;
+
+use std::collections::BTreeMap;
 
 use std::env;
+use std::process;
 use std::time::SystemTime;
 
 fn encode_float(x: f64) -> String {
@@ -18,12 +28,150 @@ fn decode_float(x: &str) -> f64 {
     x[1..].parse::<f64>().unwrap()
 }
 
+fn encode_date(x: &str) -> String {
+    format!("0{}", x)
+}
+
+fn decode_date(x: &str) -> String {
+    match x.strip_prefix("0-") {
+        Some(date) => format!("-{}", date),
+        None => x.to_string()
+    }
+}
+
+fn parse_year_start(x: &str) -> YearStart {
+    match x {
+        "december" => YearStart::DecemberSolstice,
+        "march"    => YearStart::MarchEquinox,
+        "june"     => YearStart::JuneSolstice,
+        _ => {
+            eprintln!("geodate: invalid year start '{}'", x);
+            process::exit(1)
+        }
+    }
+}
+
+// Read an epoch given by a timestamp or by a year of the gregorian epoch
+fn parse_epoch(x: &str) -> Epoch {
+    if x.contains(':') {
+        match Geodate::try_parse("%h:%y", &decode_date(x)) {
+            Ok(date) => Epoch::from_year(date.signed_year()),
+            Err(e) => fail(e)
+        }
+    } else {
+        match x.strip_prefix('0').unwrap_or(x).parse::<f64>() {
+            Ok(x) => Epoch::Custom(x as i64),
+            Err(_) => {
+                eprintln!("geodate: invalid epoch '{}'", x);
+                process::exit(1)
+            }
+        }
+    }
+}
+
+// Read an elevation in meters
+fn parse_elevation(x: &str) -> f64 {
+    match x.strip_prefix('0').unwrap_or(x).parse::<f64>() {
+        Ok(x) => x,
+        Err(_) => {
+            eprintln!("geodate: invalid elevation '{}'", x);
+            process::exit(1)
+        }
+    }
+}
+
+fn fail(e: Error) -> ! {
+    eprintln!("geodate: {}", e);
+    process::exit(1)
+}
+
+// Get the glyphs of the quarter moons, solstices, and equinoxes between two
+// times
+fn get_calendar_events(start: i64, end: i64) -> Vec<(i64, char)> {
+    let mut events = Vec::new();
+
+    let n = get_lunation_number(start).floor();
+    for k in 0..5 {
+        let k = n + k as f64 - 1.0;
+        let es = [
+            ('●', try_get_new_moon(k)),
+            ('◐', try_get_first_quarter_moon(k + 0.25)),
+            ('○', try_get_full_moon(k + 0.50)),
+            ('◑', try_get_last_quarter_moon(k + 0.75))
+        ];
+        for &(glyph, e) in es.iter() {
+            if let Ok(e) = e {
+                events.push((e, glyph));
+            }
+        }
+    }
+
+    let es = [
+        try_get_next_march_equinox(start - 1),
+        try_get_next_june_solstice(start - 1),
+        try_get_next_september_equinox(start - 1),
+        try_get_next_december_solstice(start - 1)
+    ];
+    for &e in es.iter() {
+        if let Ok(e) = e {
+            events.push((e, '*'));
+        }
+    }
+
+    events.into_iter().filter(|&(e, _)| start <= e && e < end).collect()
+}
+
+// Print the days of a month in rows of ten days
+fn print_calendar(date: &Geodate, format: &str, now: i64, lon: f64) -> Result<(), Error> {
+    let mut context = date.get_context(lon);
+    let month = match months_in_year_with_context(date.signed_year(), context.clone()).nth(date.month as usize) {
+        Some(month) => month?,
+        None => return Err(Error::NonExistentDate)
+    };
+
+    // The days are found from their middle to be safe from the approximation
+    // of the midnights
+    let (start, end) = (month.start + 43200, month.end - 43200);
+    let days: Vec<Day> = days_between_with_context(start, end, context.clone()).collect::<Result<_, _>>()?;
+
+    // A day has room for a single glyph, and the mark of a solstice or an
+    // equinox takes priority over the mark of a quarter moon on the same day
+    let mut glyphs = BTreeMap::new();
+    for (e, glyph) in get_calendar_events(month.start, month.end) {
+        if let Some(day) = days.iter().find(|day| day.start <= e && e < day.end) {
+            let previous = glyphs.entry(day.date.day).or_insert(glyph);
+            if glyph == '*' {
+                *previous = glyph;
+            }
+        }
+    }
+
+    // The title is centered above the 59 columns of the days
+    let title = try_get_formatted_date_with_context(format, start, &mut context)?;
+    let width = (59 + title.chars().count()) / 2;
+    println!("{:>width$}", title, width = width);
+    for row in days.chunks(10) {
+        let cells: Vec<String> = row.iter().map(|day| {
+            let (left, right) = if day.start <= now && now < day.end { ('[', ']') } else { (' ', ' ') };
+            let glyph = glyphs.get(&day.date.day).cloned().unwrap_or(' ');
+            format!("{}{:02}{}{}", left, day.date.day, right, glyph)
+        }).collect();
+        println!("{}", cells.join(" ").trim_end());
+    }
+    println!();
+    println!("● New Moon  ◐ First Quarter  ○ Full Moon  ◑ Last Quarter  * Solstice or Equinox");
+
+    Ok(())
+}
+
 fn main() {
     let args: Vec<String> = env::args().map(|arg|
-        // Encode float arguments that can be negative to avoid getopts panic
-        // from unrecognized options.
-        if let Some(x) = arg.parse::<f64>().ok() {
+        // Encode float and date arguments that can be negative to avoid
+        // getopts panic from unrecognized options.
+        if let Ok(x) = arg.parse::<f64>() {
             encode_float(x)
+        } else if arg.starts_with('-') && arg.contains(':') {
+            encode_date(&arg)
         } else {
             arg
         }
@@ -33,10 +181,15 @@ fn main() {
     opts.optflag("h", "help",    "print help");
     opts.optflag("v", "version", "print version");
     opts.optflag("e", "ephem",   "print ephemeris");
+    opts.optflag("c", "cal",     "print calendar of the month");
     opts.optflag("s", "solar",   "use solar calendar");
     opts.optflag("u", "unix",    "use unix epoch");
     opts.optflag("m", "machine", "use machine format");
     opts.optopt("f",  "format",  "use custom format", "<str>");
+    opts.optopt("y",  "year",    "start years at a solstice or an equinox", "december|march|june");
+    opts.optopt("",   "epoch",   "count years from a timestamp or a year", "<time>");
+    opts.optflag("",  "mean",    "use mean solar time");
+    opts.optopt("",   "elevation", "set elevation above sea level", "<meters>");
 
     let matches = match opts.parse(&args) {
         Ok(m) => { m }
@@ -56,10 +209,6 @@ fn main() {
 
     let mut format = String::from("%h:%y:%m:%d:%c:%b");
 
-    if matches.opt_present("m") {
-        format = String::from("%x");
-    }
-
     if matches.opt_present("s") {
         format = format.replace("%m", "%s");
     }
@@ -68,37 +217,107 @@ fn main() {
         format = format.replace("%y", "%u");
     }
 
+    // The calendar is printed with the months of the human format
+    let month_format = format.replace(":%d:%c:%b", "");
+
+    if matches.opt_present("m") {
+        format = String::from("%x");
+    }
+
     if matches.opt_present("f") {
         format = matches.opt_str("f").unwrap();
     }
 
+    let year_start = match matches.opt_str("y") {
+        Some(x) => parse_year_start(&x),
+        None => YearStart::default()
+    };
+
+    let epoch = match matches.opt_str("epoch") {
+        Some(x) => parse_epoch(&x),
+        None => Epoch::Gregorian
+    };
+
+    let solar_time = if matches.opt_present("mean") {
+        SolarTime::Mean
+    } else {
+        SolarTime::Apparent
+    };
+
+    let elevation = match matches.opt_str("elevation") {
+        Some(x) => parse_elevation(&x),
+        None => 0.0
+    };
+
     let lat = decode_float(&matches.free[1]);
     let lon = decode_float(&matches.free[2]);
+    let observer = match Observer::try_new(lat, lon, elevation) {
+        Ok(observer) => observer,
+        Err(e) => fail(e)
+    };
+    let mut context = GeodateContext::new(lon, epoch, Calendar::Lunisolar).with_year_start(year_start).with_solar_time(solar_time);
+
+    let now = match SystemTime::now().duration_since(SystemTime::UNIX_EPOCH) {
+        Ok(time) => time.as_secs() as i64,
+        Err(_) => 0
+    };
+
+    // Print the calendar of the current month or of a month given with its
+    // year like the beginning of a date
+    if matches.opt_present("c") {
+        let format = &month_format;
+        let date = if matches.free.len() == 4 {
+            decode_date(&matches.free[3])
+        } else {
+            match try_get_formatted_date_with_context(format, now, &mut context) {
+                Ok(date) => date,
+                Err(e) => fail(e)
+            }
+        };
+        let res = Geodate::try_parse(format, &date).and_then(|date| {
+            let epoch = if date.epoch == Epoch::Unix { Epoch::Unix } else { epoch };
+            print_calendar(&Geodate { epoch, year_start, solar_time, ..date }, format, now, lon)
+        });
+        if let Err(e) = res {
+            fail(e)
+        }
+        return;
+    }
 
     // Convert geodate string back into unix timestamp
     if matches.free.len() == 4 && matches.free[3].contains(":") {
-        println!("{}", get_timestamp(format, matches.free[3].clone(), lon));
+        match try_get_timestamp_with_format_and_context(format, decode_date(&matches.free[3]), &mut context) {
+            Ok(timestamp) => println!("{}", timestamp),
+            Err(e) => fail(e)
+        }
         return;
     }
 
     let now = if matches.free.len() == 4 {
         decode_float(&matches.free[3]) as i64
     } else {
-        match SystemTime::now().duration_since(SystemTime::UNIX_EPOCH) {
-            Ok(time) => time.as_secs() as i64,
-            Err(_) => 0
-        }
+        now
     };
 
     if matches.opt_present("e") {
-        let events = get_ephemeris(now, lon, lat);
+        let ephemeris = get_ephemeris_with_moon(now, &observer);
 
-        for (&time, name) in &events {
-            let date = get_formatted_date(&format, time, lon);
-            println!("{:20} {}", format!("{}:", name), date);
+        for &(time, event) in &ephemeris.events {
+            match try_get_formatted_date_with_context(&format, time, &mut context) {
+                Ok(date) => println!("{:20} {}", format!("{}:", event), date),
+                Err(e) => fail(e)
+            }
         }
+
+        let moon = ephemeris.moon;
+        let age = MetricDuration::from_nanoseconds((moon.age * 86400e9) as i128);
+        println!("{:20} {}", "Moon Phase:", moon.phase);
+        println!("{:20} {:.0}%", "Moon Illumination:", moon.fraction * 100.0);
+        println!("{:20} {}", "Moon Age:", age);
     } else {
-        let date = get_formatted_date(&format, now, lon);
-        println!("{}", date);
+        match try_get_formatted_date_with_context(&format, now, &mut context) {
+            Ok(date) => println!("{}", date),
+            Err(e) => fail(e)
+        }
     }
 }
@@ -1,14 +1,25 @@
 use math::*;
 use julian::*;
 use delta_time::*;
+use error::Error;
+use moon_transit::get_moon_position;
+use sun_position::get_sun_equatorial_coordinates;
+use timestamp::Timestamp;
 
+use core::fmt;
 use core::ops::Rem;
 #[cfg(not(feature = "std"))]
 use num_traits::Float;
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+// Kilometers in an astronomical unit
+const AU: f64 = 149_597_870.7;
 
 #[repr(usize)]
+#[allow(clippy::enum_variant_names)]
 #[derive(Clone, Copy)]
-enum MoonPhase {
+pub(crate) enum MoonPhase {
     NewMoon,
     FirstQuarterMoon,
     FullMoon,
@@ -17,7 +28,8 @@ enum MoonPhase {
 
 // From "Astronomical Algorithms"
 // By Jean Meeus
-fn get_time_of(phase: MoonPhase, lunation_number: f64) -> i64 {
+#[allow(clippy::inconsistent_digit_grouping)]
+pub(crate) fn get_time_of(phase: MoonPhase, lunation_number: f64, table: Option<&DeltaTimeTable>) -> Result<i64, Error> {
     /*
     // TODO: use `lunation_number: i64`
     let k = match phase {
@@ -208,23 +220,150 @@ fn get_time_of(phase: MoonPhase, lunation_number: f64) -> i64 {
 
     let jde = jde + cor + add;
 
-    terrestrial_to_universal_time(julian_to_unix(jde))
+    try_terrestrial_to_universal_time_with_table(julian_to_unix(jde), table)
 }
 
 pub fn get_new_moon(lunation_number: f64) -> i64 {
-    get_time_of(MoonPhase::NewMoon, lunation_number)
+    try_get_new_moon(lunation_number).unwrap()
+}
+
+pub fn try_get_new_moon(lunation_number: f64) -> Result<i64, Error> {
+    get_time_of(MoonPhase::NewMoon, lunation_number, None)
 }
 
 pub fn get_first_quarter_moon(lunation_number: f64) -> i64 {
-    get_time_of(MoonPhase::FirstQuarterMoon, lunation_number)
+    try_get_first_quarter_moon(lunation_number).unwrap()
+}
+
+pub fn try_get_first_quarter_moon(lunation_number: f64) -> Result<i64, Error> {
+    get_time_of(MoonPhase::FirstQuarterMoon, lunation_number, None)
 }
 
 pub fn get_full_moon(lunation_number: f64) -> i64 {
-    get_time_of(MoonPhase::FullMoon, lunation_number)
+    try_get_full_moon(lunation_number).unwrap()
+}
+
+pub fn try_get_full_moon(lunation_number: f64) -> Result<i64, Error> {
+    get_time_of(MoonPhase::FullMoon, lunation_number, None)
 }
 
 pub fn get_last_quarter_moon(lunation_number: f64) -> i64 {
-    get_time_of(MoonPhase::LastQuarterMoon, lunation_number)
+    try_get_last_quarter_moon(lunation_number).unwrap()
+}
+
+pub fn try_get_last_quarter_moon(lunation_number: f64) -> Result<i64, Error> {
+    get_time_of(MoonPhase::LastQuarterMoon, lunation_number, None)
+}
+
+/// The name of the phase of the Moon, each lasting an eighth of a lunation
+/// centered on the principal phases for the new, quarter, and full moons
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum LunarPhase {
+    NewMoon,
+    WaxingCrescent,
+    FirstQuarter,
+    WaxingGibbous,
+    FullMoon,
+    WaningGibbous,
+    LastQuarter,
+    WaningCrescent
+}
+
+impl fmt::Display for LunarPhase {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            LunarPhase::NewMoon        => "New Moon",
+            LunarPhase::WaxingCrescent => "Waxing Crescent",
+            LunarPhase::FirstQuarter   => "First Quarter",
+            LunarPhase::WaxingGibbous  => "Waxing Gibbous",
+            LunarPhase::FullMoon       => "Full Moon",
+            LunarPhase::WaningGibbous  => "Waning Gibbous",
+            LunarPhase::LastQuarter    => "Last Quarter",
+            LunarPhase::WaningCrescent => "Waning Crescent"
+        };
+        write!(f, "{}", name)
+    }
+}
+
+/// The illumination of the Moon seen from the center of the Earth
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct MoonIllumination {
+    /// Illuminated fraction of the disk, from 0 at new moon to 1 at full
+    /// moon
+    pub fraction: f64,
+    /// Angle in degrees between the Sun and the Earth seen from the Moon
+    pub phase_angle: f64,
+    /// Position angle in degrees of the midpoint of the bright limb,
+    /// measured eastward from the north point of the disk
+    pub bright_limb_angle: f64,
+    /// Days since the last new moon
+    pub age: f64,
+    /// Name of the phase
+    pub phase: LunarPhase
+}
+
+/// Get the illumination of the Moon
+pub fn get_moon_illumination<T: Timestamp>(timestamp: T) -> MoonIllumination {
+    try_get_moon_illumination(timestamp).unwrap()
+}
+
+/// Get the illumination of the Moon
+pub fn try_get_moon_illumination<T: Timestamp>(timestamp: T) -> Result<MoonIllumination, Error> {
+    let timestamp = timestamp.unix_timestamp();
+    let jde = unix_to_julian(timestamp) + try_delta_time(unix_to_year(timestamp))? / 86400.0;
+
+    let (a0, d0, r) = get_sun_equatorial_coordinates(jde);
+    let (a, d, dist) = get_moon_position(jde);
+
+    // Geocentric elongation of the Moon from the Sun
+    // (formula 48.2)
+    // (ψ)
+    let psi = acos_deg(sin_deg(d0) * sin_deg(d) + cos_deg(d0) * cos_deg(d) * cos_deg(a0 - a));
+
+    // Phase angle
+    // (formula 48.3)
+    // (i)
+    let r = r * AU;
+    let i = atan2_deg(r * sin_deg(psi), dist - r * cos_deg(psi));
+
+    // Illuminated fraction
+    // (formula 48.1)
+    // (k)
+    let k = (1.0 + cos_deg(i)) / 2.0;
+
+    // Position angle of the bright limb
+    // (formula 48.5)
+    // (χ)
+    let chi = atan2_deg(
+        cos_deg(d0) * sin_deg(a0 - a),
+        sin_deg(d0) * cos_deg(d) - cos_deg(d0) * sin_deg(d) * cos_deg(a0 - a)
+    );
+
+    // The eighths of the lunation are found from the elongation of the Moon
+    // east of the Sun in right ascension
+    let elongation = modulo(a - a0, 360.0);
+    let phase = match ((elongation + 22.5) / 45.0).floor() as usize % 8 {
+        0 => LunarPhase::NewMoon,
+        1 => LunarPhase::WaxingCrescent,
+        2 => LunarPhase::FirstQuarter,
+        3 => LunarPhase::WaxingGibbous,
+        4 => LunarPhase::FullMoon,
+        5 => LunarPhase::WaningGibbous,
+        6 => LunarPhase::LastQuarter,
+        _ => LunarPhase::WaningCrescent
+    };
+
+    let age = (timestamp - try_get_previous_new_moon(timestamp)?) as f64 / 86400.0;
+
+    Ok(MoonIllumination {
+        fraction: k,
+        phase_angle: i,
+        bright_limb_angle: modulo(chi, 360.0),
+        age,
+        phase
+    })
 }
 
 /*
@@ -239,22 +378,43 @@ enum LunationNumbering {
 */
 
 /// Computes the Lunation Number since the first new moon of 2000
-pub fn get_lunation_number(timestamp: i64) -> f64 {
-    ((unix_to_year(timestamp) - 2000.0) * 12.3685).floor() // TODO: `as i64`
+pub fn get_lunation_number<T: Timestamp>(timestamp: T) -> f64 {
+    ((unix_to_year(timestamp.unix_timestamp()) - 2000.0) * 12.3685).floor() // TODO: `as i64`
+}
+
+pub fn get_previous_new_moon<T: Timestamp>(timestamp: T) -> i64 {
+    try_get_previous_new_moon(timestamp).unwrap()
+}
+
+pub fn try_get_previous_new_moon<T: Timestamp>(timestamp: T) -> Result<i64, Error> {
+    let timestamp = timestamp.unix_timestamp();
+    let mut n = get_lunation_number(timestamp) + 2.0;
+    let mut new_moon = try_get_new_moon(n)?;
+    while new_moon > timestamp {
+        n -= 1.0;
+        new_moon = try_get_new_moon(n)?;
+    }
+    Ok(new_moon)
+}
+
+pub fn get_next_new_moon<T: Timestamp>(timestamp: T) -> i64 {
+    try_get_next_new_moon(timestamp).unwrap()
 }
 
-pub fn get_next_new_moon(timestamp: i64) -> i64 {
-    let new_moon = get_new_moon(get_lunation_number(timestamp));
+pub fn try_get_next_new_moon<T: Timestamp>(timestamp: T) -> Result<i64, Error> {
+    let timestamp = timestamp.unix_timestamp();
+    let new_moon = try_get_new_moon(get_lunation_number(timestamp))?;
     if new_moon > timestamp {
-        new_moon
+        Ok(new_moon)
     } else {
-        get_new_moon(get_lunation_number(timestamp) + 1.0)
+        try_get_new_moon(get_lunation_number(timestamp) + 1.0)
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use alloc::string::ToString;
     use utils::*;
 
     #[test]
@@ -314,6 +474,65 @@ mod tests {
         assert_eq!(t, get_new_moon(get_lunation_number(0) + 1.0));
     }
 
+    #[test]
+    fn try_get_next_new_moon_test() {
+        assert_eq!(Ok(get_next_new_moon(0)), try_get_next_new_moon(0));
+        assert_eq!(Err(Error::OutOfRange), try_get_next_new_moon(parse_time("3500-01-01T00:00:00.00+00:00")));
+    }
+
+    #[test]
+    fn get_previous_new_moon_test() {
+        // First new moon of 1970
+        let t = parse_time("1970-01-07T20:35:27.00+00:00");
+        assert_eq!(t, get_previous_new_moon(t));
+        assert_eq!(t, get_previous_new_moon(t + 29 * 86400));
+        assert_eq!(t, get_next_new_moon(get_previous_new_moon(t - 1)));
+        assert_eq!(Err(Error::OutOfRange), try_get_previous_new_moon(parse_time("3500-01-01T00:00:00.00+00:00")));
+    }
+
+    #[test]
+    fn get_moon_illumination_test() {
+        // Example 48.a from "Astronomical Algoritms"
+        let t = parse_time("1992-04-12T00:00:00+00:00") - 59;
+        let illumination = get_moon_illumination(t);
+        assert_approx_eq!(0.6786, illumination.fraction, 0.000_1);
+        assert_approx_eq!(69.0756, illumination.phase_angle, 0.001);
+        assert_approx_eq!(285.0, illumination.bright_limb_angle, 0.1);
+        assert_eq!(LunarPhase::WaxingGibbous, illumination.phase);
+
+        assert_eq!(Err(Error::OutOfRange), try_get_moon_illumination(parse_time("3500-01-01T00:00:00.00+00:00")));
+    }
+
+    #[test]
+    fn get_moon_illumination_phases_test() {
+        let n = get_lunation_number(parse_time("2014-06-21T00:00:00+00:00"));
+        let new_moon = get_new_moon(n);
+        let phases = [
+            (get_new_moon(n), LunarPhase::NewMoon, 0.0),
+            (get_first_quarter_moon(n + 0.25), LunarPhase::FirstQuarter, 0.5),
+            (get_full_moon(n + 0.50), LunarPhase::FullMoon, 1.0),
+            (get_last_quarter_moon(n + 0.75), LunarPhase::LastQuarter, 0.5)
+        ];
+        for &(t, phase, fraction) in phases.iter() {
+            let illumination = get_moon_illumination(t);
+            assert_eq!(phase, illumination.phase);
+            assert_approx_eq!(fraction, illumination.fraction, 0.01);
+            assert_approx_eq!((t - new_moon) as f64 / 86400.0, illumination.age, 0.000_1);
+
+            // The phases in between are crescent or gibbous
+            let illumination = get_moon_illumination(t + 4 * 86400);
+            assert!(illumination.phase as usize == phase as usize + 1);
+        }
+        assert_eq!("Waxing Crescent", LunarPhase::WaxingCrescent.to_string());
+
+        // The bright limb faces the Sun, to the west when waxing and to the
+        // east when waning
+        let waxing = get_moon_illumination(phases[1].0).bright_limb_angle;
+        let waning = get_moon_illumination(phases[3].0).bright_limb_angle;
+        assert!(180.0 < waxing && waxing < 360.0);
+        assert!(0.0 < waning && waning < 180.0);
+    }
+
     #[test]
     fn get_last_quarter_moon_test() {
         // Example 49.b from "Astronomical Algoritms"
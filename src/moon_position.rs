@@ -0,0 +1,123 @@
+use coordinates::*;
+use delta_time::*;
+use error::Error;
+use julian::*;
+use math::*;
+use moon_transit;
+use observer::Observer;
+use timestamp::Timestamp;
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+/// The position of the Moon in the sky of an observer
+///
+/// The geocentric coordinates are seen from the center of the Earth, while
+/// the topocentric ones are seen from the observer, which shifts the Moon
+/// by up to about one degree.
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct MoonPosition {
+    /// Geocentric apparent right ascension in degrees
+    pub right_ascension: f64,
+    /// Geocentric apparent declination in degrees
+    pub declination: f64,
+    /// Topocentric apparent right ascension in degrees
+    pub topocentric_right_ascension: f64,
+    /// Topocentric apparent declination in degrees
+    pub topocentric_declination: f64,
+    /// Topocentric azimuth in degrees measured clockwise from the north
+    pub azimuth: f64,
+    /// Topocentric altitude of the center of the Moon in degrees above the
+    /// horizon, without the atmospheric refraction
+    pub altitude: f64,
+    /// Distance between the centers of the Earth and the Moon in kilometers
+    pub distance: f64,
+    /// Equatorial horizontal parallax in degrees
+    pub parallax: f64
+}
+
+impl MoonPosition {
+    /// Get the altitude of the center of the Moon raised by the atmospheric
+    /// refraction, as seen in the sky
+    pub fn apparent_altitude(&self) -> f64 {
+        self.altitude + get_refraction(self.altitude)
+    }
+}
+
+/// Get the position of the Moon seen by an observer
+pub fn get_moon_position<T: Timestamp>(observer: &Observer, timestamp: T) -> MoonPosition {
+    try_get_moon_position(observer, timestamp).unwrap()
+}
+
+/// Get the position of the Moon seen by an observer
+pub fn try_get_moon_position<T: Timestamp>(observer: &Observer, timestamp: T) -> Result<MoonPosition, Error> {
+    let timestamp = timestamp.unix_timestamp();
+
+    // Julian day and julian ephemeris day
+    let jd = unix_to_julian(timestamp);
+    let jde = jd + try_delta_time(unix_to_year(timestamp))? / 86400.0;
+
+    let (a, d, dist) = moon_transit::get_moon_position(jde);
+    let a = modulo(a, 360.0);
+
+    // Equatorial horizontal parallax
+    // (π)
+    let p = asin_deg(6378.14 / dist);
+
+    let th0 = get_sidereal_time(jd);
+    let (ta, td) = get_topocentric_coordinates(observer, th0, a, d, p);
+    let (azimuth, altitude) = get_horizontal_coordinates(observer, th0, ta, td);
+
+    Ok(MoonPosition {
+        right_ascension: a,
+        declination: d,
+        topocentric_right_ascension: ta,
+        topocentric_declination: td,
+        azimuth,
+        altitude,
+        distance: dist,
+        parallax: p
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use moon_transit::{get_moonrise, get_moonset};
+    use utils::*;
+
+    #[test]
+    fn get_moon_position_test() {
+        // Example 47.a from "Astronomical Algoritms"
+        let observer = Observer::new(0.0, 0.0, 0.0);
+        let t = parse_time("1992-04-12T00:00:00+00:00") - 59;
+        let position = get_moon_position(&observer, t);
+        assert_approx_eq!(134.688_470, position.right_ascension, 0.000_1);
+        assert_approx_eq!(13.768_368, position.declination, 0.000_1);
+        assert_approx_eq!(368_409.7, position.distance, 0.1);
+        assert_approx_eq!(0.991_990, position.parallax, 0.000_01);
+
+        assert_eq!(Err(Error::OutOfRange), try_get_moon_position(&observer, parse_time("3100-01-01T00:00:00+00:00")));
+    }
+
+    #[test]
+    fn get_moon_position_in_the_sky_test() {
+        let t = parse_time("2015-06-21T12:00:00+00:00");
+
+        // The Moon is lowered by its parallax by almost one degree on the
+        // horizon, where its center is below the horizon at moonrise
+        let observer = Observer::new(45.0, 0.0, 0.0);
+        let moonrise = get_moonrise(t, 0.0, 45.0).unwrap();
+        let position = get_moon_position(&observer, moonrise);
+        let shift = (position.declination - position.topocentric_declination).abs()
+                  + (position.right_ascension - position.topocentric_right_ascension).abs();
+        assert!(shift > 0.5);
+        assert_approx_eq!(-0.2725 * position.parallax - 0.5667, position.altitude, 0.5);
+        assert!(position.apparent_altitude() > position.altitude);
+        assert!(position.azimuth < 180.0);
+
+        let moonset = get_moonset(t, 0.0, 45.0).unwrap();
+        assert!(get_moon_position(&observer, moonset).azimuth > 180.0);
+    }
+}
@@ -2,6 +2,8 @@ use julian::*;
 use math::*;
 use sun_transit::*;
 use delta_time::*;
+use observer::*;
+use timestamp::Timestamp;
 
 #[cfg(not(feature = "std"))]
 use num_traits::Float;
@@ -12,7 +14,10 @@ enum Event {
     Moonset
 }
 
-fn get_moon_position(julian_day: f64) -> (f64, f64, f64) {
+// Get the apparent right ascension and declination of the Moon in degrees,
+// and its distance in kilometers, at a julian ephemeris day
+#[allow(clippy::inconsistent_digit_grouping)]
+pub(crate) fn get_moon_position(julian_day: f64) -> (f64, f64, f64) {
     // Julian century
     let t = jde_to_julian_century(julian_day);
 
@@ -267,7 +272,7 @@ fn get_moon_position(julian_day: f64) -> (f64, f64, f64) {
     (a, s, d)
 }
 
-fn get_time_of(event: Event, timestamp: i64, longitude: f64, latitude: f64) -> Option<i64> {
+fn get_time_of(event: Event, timestamp: i64, longitude: f64, latitude: f64, elevation: f64) -> Option<i64> {
     let lon = -longitude; // Longitude of the observer, measured positively west
     let lat = latitude; // Latitude of the observer, measured positively north
 
@@ -289,12 +294,12 @@ fn get_time_of(event: Event, timestamp: i64, longitude: f64, latitude: f64) -> O
 
     // Geometric altitude of the center of the body
     //let h0 = 0.125; // Low accuracy
-    let h0 = 0.7275 * p - dec_deg(0.0, 34.0, 0.0);
+    let h0 = 0.7275 * p - dec_deg(0.0, 34.0, 0.0) - get_horizon_dip(elevation);
 
     // H0
     let cos_hh0 = (sin_deg(h0) - sin_deg(lat) * sin_deg(dec2))
                 / (cos_deg(lat) * cos_deg(dec2));
-    if cos_hh0 < -1.0 || cos_hh0 > 1.0 {
+    if !(-1.0..=1.0).contains(&cos_hh0) {
         return None
     }
     let hh0 = acos_deg(cos_hh0);
@@ -350,12 +355,24 @@ fn get_time_of(event: Event, timestamp: i64, longitude: f64, latitude: f64) -> O
     Some(julian_to_unix(jd + m + dm))
 }
 
-pub fn get_moonrise(timestamp: i64, longitude: f64, latitude: f64) -> Option<i64> {
-    get_time_of(Event::Moonrise, timestamp, longitude, latitude)
+pub fn get_moonrise<T: Timestamp>(timestamp: T, longitude: f64, latitude: f64) -> Option<i64> {
+    get_time_of(Event::Moonrise, timestamp.unix_timestamp(), longitude, latitude, 0.0)
 }
 
-pub fn get_moonset(timestamp: i64, longitude: f64, latitude: f64) -> Option<i64> {
-    get_time_of(Event::Moonset, timestamp, longitude, latitude)
+pub fn get_moonset<T: Timestamp>(timestamp: T, longitude: f64, latitude: f64) -> Option<i64> {
+    get_time_of(Event::Moonset, timestamp.unix_timestamp(), longitude, latitude, 0.0)
+}
+
+/// Get the moonrise seen by an observer above the dip of its horizon
+pub fn get_moonrise_with_observer<T: Timestamp>(timestamp: T, observer: &Observer) -> Option<i64> {
+    let (lon, lat, h) = (observer.longitude(), observer.latitude(), observer.elevation());
+    get_time_of(Event::Moonrise, timestamp.unix_timestamp(), lon, lat, h)
+}
+
+/// Get the moonset seen by an observer above the dip of its horizon
+pub fn get_moonset_with_observer<T: Timestamp>(timestamp: T, observer: &Observer) -> Option<i64> {
+    let (lon, lat, h) = (observer.longitude(), observer.latitude(), observer.elevation());
+    get_time_of(Event::Moonset, timestamp.unix_timestamp(), lon, lat, h)
 }
 
 #[cfg(test)]
@@ -396,4 +413,17 @@ mod tests {
             assert_approx_eq!(get_moonrise(parse_time(t1), lon, lat).unwrap(), parse_time(t0), accuracy);
         }
     }
+
+    #[test]
+    fn get_moonrise_with_observer_test() {
+        let t = parse_time("2015-06-21T12:00:00+00:00");
+        let observer = Observer::new(45.0, 0.0, 0.0);
+        assert_eq!(get_moonrise(t, 0.0, 45.0), get_moonrise_with_observer(t, &observer));
+        assert_eq!(get_moonset(t, 0.0, 45.0), get_moonset_with_observer(t, &observer));
+
+        // The moon rises earlier and sets later from a mountain
+        let observer = Observer::new(45.0, 0.0, 2000.0);
+        assert!(get_moonrise_with_observer(t, &observer).unwrap() < get_moonrise(t, 0.0, 45.0).unwrap() - 300);
+        assert!(get_moonset_with_observer(t, &observer).unwrap() > get_moonset(t, 0.0, 45.0).unwrap() + 300);
+    }
 }
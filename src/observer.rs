@@ -0,0 +1,138 @@
+use error::Error;
+
+use core::convert::TryFrom;
+#[cfg(not(feature = "std"))]
+use num_traits::Float;
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+/// A place on the Earth from which the sky is observed
+///
+/// The latitude is measured in degrees positively north, the longitude in
+/// degrees positively east, and the elevation in meters above sea level.
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(try_from = "Coordinates"))]
+pub struct Observer {
+    latitude: f64,
+    longitude: f64,
+    elevation: f64
+}
+
+// Unchecked fields of an observer
+#[cfg_attr(feature = "serde", derive(Deserialize))]
+struct Coordinates {
+    latitude: f64,
+    longitude: f64,
+    #[cfg_attr(feature = "serde", serde(default))]
+    elevation: f64
+}
+
+impl Observer {
+    /// Create an observer at a latitude, a longitude, and an elevation
+    pub fn new(latitude: f64, longitude: f64, elevation: f64) -> Observer {
+        Observer::try_new(latitude, longitude, elevation).unwrap()
+    }
+
+    /// Create an observer at a latitude, a longitude, and an elevation
+    ///
+    /// The latitude must be between -90° and 90°, the longitude between
+    /// -180° and 180°, and the elevation must be finite.
+    pub fn try_new(latitude: f64, longitude: f64, elevation: f64) -> Result<Observer, Error> {
+        Observer::try_from(Coordinates { latitude, longitude, elevation })
+    }
+
+    // Create an observer without checking its coordinates for the functions
+    // still taking loose arguments
+    pub(crate) fn new_unchecked(latitude: f64, longitude: f64, elevation: f64) -> Observer {
+        Observer { latitude, longitude, elevation }
+    }
+
+    pub fn latitude(&self) -> f64 {
+        self.latitude
+    }
+
+    pub fn longitude(&self) -> f64 {
+        self.longitude
+    }
+
+    pub fn elevation(&self) -> f64 {
+        self.elevation
+    }
+
+    /// Get the dip of the horizon below the horizontal plane in degrees
+    ///
+    /// An observer above sea level sees the Sun and the Moon rise earlier
+    /// and set later than at sea level.
+    pub fn horizon_dip(&self) -> f64 {
+        get_horizon_dip(self.elevation)
+    }
+}
+
+impl TryFrom<Coordinates> for Observer {
+    type Error = Error;
+
+    fn try_from(c: Coordinates) -> Result<Observer, Error> {
+        if !(-90.0..=90.0).contains(&c.latitude) {
+            return Err(Error::InvalidLatitude);
+        }
+        if !(-180.0..=180.0).contains(&c.longitude) {
+            return Err(Error::InvalidLongitude);
+        }
+        if !c.elevation.is_finite() {
+            return Err(Error::InvalidElevation);
+        }
+        Ok(Observer { latitude: c.latitude, longitude: c.longitude, elevation: c.elevation })
+    }
+}
+
+// Get the dip of the horizon in degrees for an elevation in meters, with
+// no dip below sea level
+pub fn get_horizon_dip(elevation: f64) -> f64 {
+    if elevation > 0.0 {
+        2.076 * elevation.sqrt() / 60.0
+    } else {
+        0.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn observer_test() {
+        let observer = Observer::new(51.178844, -1.826189, 102.0);
+        assert_eq!(51.178844, observer.latitude());
+        assert_eq!(-1.826189, observer.longitude());
+        assert_eq!(102.0, observer.elevation());
+        assert_eq!(Ok(observer), Observer::try_new(51.178844, -1.826189, 102.0));
+
+        assert!(Observer::try_new(-90.0, 180.0, -430.0).is_ok());
+        assert_eq!(Err(Error::InvalidLatitude), Observer::try_new(90.5, 0.0, 0.0));
+        assert_eq!(Err(Error::InvalidLongitude), Observer::try_new(0.0, -181.0, 0.0));
+        assert_eq!(Err(Error::InvalidLatitude), Observer::try_new(f64::NAN, 0.0, 0.0));
+        assert_eq!(Err(Error::InvalidElevation), Observer::try_new(0.0, 0.0, f64::INFINITY));
+    }
+
+    #[test]
+    fn horizon_dip_test() {
+        assert_eq!(0.0, Observer::new(0.0, 0.0, 0.0).horizon_dip());
+        assert_eq!(0.0, Observer::new(0.0, 0.0, -430.0).horizon_dip());
+        assert_approx_eq!(1.094, Observer::new(0.0, 0.0, 1000.0).horizon_dip(), 0.001);
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn observer_serde_test() {
+        let observer = Observer::new(51.178844, -1.826189, 102.0);
+        let json = r#"{"latitude":51.178844,"longitude":-1.826189,"elevation":102.0}"#;
+        assert_eq!(json, serde_json::to_string(&observer).unwrap());
+        assert_eq!(observer, serde_json::from_str(json).unwrap());
+
+        // The elevation is optional but the coordinates are checked
+        let json = r#"{"latitude":51.178844,"longitude":-1.826189}"#;
+        assert_eq!(Observer::new(51.178844, -1.826189, 0.0), serde_json::from_str(json).unwrap());
+        assert!(serde_json::from_str::<Observer>(r#"{"latitude":91.0,"longitude":0.0}"#).is_err());
+    }
+}
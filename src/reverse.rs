@@ -1,106 +1,402 @@
 use geodate::*;
+use calendar::*;
+use context::GeodateContext;
+use error::Error;
+use format::parse_date;
 
 use alloc::string::String;
-use alloc::vec::Vec;
 
 /// Reverse a geodate into a timestamp
+///
+/// This function panics for a malformed date or a date that does not exist
+/// in the calendar, see `try_get_timestamp`.
 pub fn get_timestamp(format: String, date: String, longitude: f64) -> i64 {
-    let y = date_year(date.clone());
-    let n = date_index(date.clone());
-
-    // Approximate timestamps of bounds
-    let mut min = (y - 2) * 365 * 86400;
-    let mut max = (y + 2) * 365 * 86400;
-    let epoch = if format.contains("%y") {
-        // 1900 - 1970
-        min -= 70 * 365 * 86400;
-        max -= 70 * 365 * 86400;
-
-        -2208988580 // 1900-01-01T00:03:40+0000
-    } else {
-        518780 // 1970-01-07T00:06:20+0000
-    };
-    if min < epoch && epoch < max {
-        if date.starts_with("-") {
-            max = epoch - 9;
-        } else {
-            min = epoch;
-        }
+    try_get_timestamp(format, date, longitude).unwrap()
+}
+
+/// Reverse a geodate into a timestamp
+///
+/// The date is read following the format, see `get_formatted_date`. A date
+/// that does not exist in the calendar, such as the day 29 of a month of 29
+/// days, returns an error instead of the nearest timestamp.
+pub fn try_get_timestamp(format: String, date: String, longitude: f64) -> Result<i64, Error> {
+    try_get_timestamp_with_year_start(format, date, longitude, YearStart::default())
+}
+
+/// Reverse a geodate with years starting at the given solstice or equinox
+/// into a timestamp
+pub fn get_timestamp_with_year_start(format: String, date: String, longitude: f64, year_start: YearStart) -> i64 {
+    try_get_timestamp_with_year_start(format, date, longitude, year_start).unwrap()
+}
+
+/// Reverse a geodate with years starting at the given solstice or equinox
+/// into a timestamp
+///
+/// The date is read following the format, see `get_formatted_date`.
+pub fn try_get_timestamp_with_year_start(format: String, date: String, longitude: f64, year_start: YearStart) -> Result<i64, Error> {
+    let context = GeodateContext::new(longitude, Epoch::Gregorian, Calendar::Lunisolar);
+    try_get_timestamp_with_format_and_context(format, date, &mut context.with_year_start(year_start))
+}
+
+/// Reverse a geodate into a timestamp with the configuration of a context
+pub fn get_timestamp_with_format_and_context(format: String, date: String, context: &mut GeodateContext) -> i64 {
+    try_get_timestamp_with_format_and_context(format, date, context).unwrap()
+}
+
+/// Reverse a geodate into a timestamp with the configuration of a context
+///
+/// The date is read following the format, see `get_formatted_date`. The
+/// epoch and the calendar of the context are used unless the format has
+/// the `%u` or `%s` specifiers.
+pub fn try_get_timestamp_with_format_and_context(format: String, date: String, context: &mut GeodateContext) -> Result<i64, Error> {
+    let parsed = parse_date(&format, &date)?;
+    if let Some(timestamp) = parsed.timestamp {
+        return Ok(timestamp);
     }
 
-    loop {
-        let mid = (min + max) / 2;
-        let i = date_index(get_formatted_date(&format, mid, longitude));
-        if i == n || mid == min || mid == max {
-            return mid;
-        }
-        if i < n {
-            min = mid;
-        } else {
-            max = mid;
-        }
+    let mut date = parsed.date;
+    if date.epoch == Epoch::Gregorian {
+        date.epoch = context.epoch;
+    }
+    if date.calendar == Calendar::Lunisolar {
+        date.calendar = context.calendar;
     }
+    date.year_start = context.year_start;
+    date.solar_time = context.solar_time;
+    try_get_timestamp_with_context(&date, context)
 }
 
-// Extract year from a geodate string
-fn date_year(date: String) -> i64 {
-    let parts: Vec<_> = date.split(":").collect();
+/// Reverse a geodate into a timestamp with the configuration of a context
+pub fn get_timestamp_with_context(date: &Geodate, context: &mut GeodateContext) -> i64 {
+    try_get_timestamp_with_context(date, context).unwrap()
+}
 
-    let y = match parts.len() {
-        6 => format!("{}{}", parts[0], parts[1]),
-        5 => format!("{}", parts[0]),
-        _ => panic!("wrong date format")
-    };
+/// Reverse a geodate into a timestamp with the configuration of a context
+///
+/// The timestamp returned is the first second of the dimiday of the date,
+/// and an error is returned for a month or a day outside of the calendar.
+/// The epoch, calendar, year start, and solar time of the date are used
+/// instead of those of the context when they differ.
+pub fn try_get_timestamp_with_context(date: &Geodate, context: &mut GeodateContext) -> Result<i64, Error> {
+    if (date.epoch, date.calendar, date.year_start, date.solar_time) != (context.epoch, context.calendar, context.year_start, context.solar_time) {
+        let mut other_context = date.get_context(context.longitude);
+        other_context.delta_time_table = context.delta_time_table.clone();
+        return try_get_timestamp_with_context(date, &mut other_context);
+    }
 
-    y.parse::<i64>().unwrap()
-}
+    let (lon, st) = (context.longitude, context.solar_time);
 
-// Transform a geodate string into an integer for comparison
-fn date_index(date: String) -> i64 {
-    let year = date_year(date.clone());
-    let mut index = date.replace(":", "").parse::<i64>().unwrap();
-    if index < 0 { // Special case for negative years
-        index = (year + 0) * 100_000_000 - (index % 100_000_000);
+    let solstice = try_get_year_solstice(context, date.signed_year())?;
+    let months = try_get_month_starts(context, solstice)?;
+
+    let m = date.month as usize;
+    if date.month < 0 || m + 1 >= months.len() {
+        return Err(Error::NonExistentDate);
+    }
+    if date.day < 0 || date.day >= count_days(months[m], months[m + 1]) {
+        return Err(Error::NonExistentDate);
     }
-    index
+    if date.centiday < 0 || date.centiday > 99 || date.dimiday < 0 || date.dimiday > 99 {
+        return Err(Error::NonExistentDate);
+    }
+
+    let midnight = get_day_start(months[m] + date.day * 86400 + 43200, lon, st);
+    let previous_midnight = get_day_start(midnight - 43200, lon, st);
+    let next_midnight = get_day_start(midnight + 86400 + 43200, lon, st);
+
+    // First second of the dimiday after midnight
+    let e = date.centiday * 100 + date.dimiday;
+    let offset = (e * 86400 + 9999) / 10000;
+
+    // The midnight of a timestamp at the beginning or the end of the day can
+    // be approximated from the midnight of the previous or the next day by
+    // a mean solar day
+    let mut midnights = [midnight, previous_midnight + 86400, next_midnight - 86400];
+    midnights.sort();
+    let timestamp = midnights.iter().map(|&midnight| {
+        midnight + offset
+    }).find(|&timestamp| {
+        get_day_start(timestamp, lon, st) == timestamp - offset
+    }).ok_or(Error::NonExistentDate)?;
+
+    let res = Geodate::try_new_with_context(timestamp, context)?;
+    if (res.signed_year(), res.month, res.day, res.centiday, res.dimiday) != (date.signed_year(), date.month, date.day, date.centiday, date.dimiday) {
+        return Err(Error::NonExistentDate);
+    }
+
+    Ok(timestamp)
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use alloc::string::ToString;
+    use alloc::vec::Vec;
+    use earth_orbit::*;
+    use utils::*;
+
+    fn parse(date: &str) -> Geodate {
+        let format = match date.matches(':').count() {
+            5 => "%h:%y:%m:%d:%c:%b",
+            _ => "%y:%m:%d:%c:%b"
+        };
+        Geodate::try_parse(format, date).unwrap()
+    }
 
     #[test]
     fn date_year_test() {
-        assert_eq!(date_year(    "00:00:00:00:00".into()),     0);
-        assert_eq!(date_year(    "02:00:00:00:00".into()),     2);
-        assert_eq!(date_year(    "42:00:00:00:00".into()),    42);
+        assert_eq!(parse(    "00:00:00:00:00").signed_year(),     0);
+        assert_eq!(parse(    "02:00:00:00:00").signed_year(),     2);
+        assert_eq!(parse(    "42:00:00:00:00").signed_year(),    42);
+
+        assert_eq!(parse(   "-00:00:00:00:00").signed_year(),     0);
+        assert_eq!(parse(   "-02:00:00:00:00").signed_year(),    -2);
+        assert_eq!(parse(   "-42:00:00:00:00").signed_year(),   -42);
+
+        assert_eq!(parse( "00:00:00:00:00:00").signed_year(),     0);
+        assert_eq!(parse( "00:02:00:00:00:00").signed_year(),     2);
+        assert_eq!(parse( "00:42:00:00:00:00").signed_year(),    42);
+        assert_eq!(parse( "03:37:00:00:00:00").signed_year(),   337);
+        assert_eq!(parse( "13:37:00:00:00:00").signed_year(),  1337);
+
+        assert_eq!(parse("-00:00:00:00:00:00").signed_year(),     0);
+        assert_eq!(parse("-00:02:00:00:00:00").signed_year(),    -2);
+        assert_eq!(parse("-00:42:00:00:00:00").signed_year(),   -42);
+        assert_eq!(parse("-03:37:00:00:00:00").signed_year(),  -337);
+        assert_eq!(parse("-13:37:00:00:00:00").signed_year(), -1337);
+    }
+
+    #[test]
+    fn date_order_test() {
+        let dates = [
+            parse("-00:02:00:00:00:00"),
+            parse("-00:02:05:00:00:00"),
+            parse("-00:02:10:00:00:00"),
+            parse("-00:01:00:00:00:00"),
+            parse("-00:01:10:00:00:00"),
+            parse("-00:01:11:28:99:99"),
+            parse( "00:00:00:00:00:00"),
+            parse( "00:02:00:00:00:00")
+        ];
+        for i in 1..dates.len() {
+            assert!(dates[i - 1] < dates[i]);
+        }
+    }
+
+    #[test]
+    fn try_get_timestamp_test() {
+        let format = String::from("%h:%y:%m:%d:%c:%b");
+        let date = get_formatted_date(&format, 1403322675, -1.826189);
+        let timestamp = try_get_timestamp(format.clone(), date.clone(), -1.826189).unwrap();
+        assert_eq!(date, get_formatted_date(&format, timestamp, -1.826189));
 
-        assert_eq!(date_year(   "-00:00:00:00:00".into()),     0);
-        assert_eq!(date_year(   "-02:00:00:00:00".into()),    -2);
-        assert_eq!(date_year(   "-42:00:00:00:00".into()),   -42);
+        assert_eq!(Err(Error::MalformedDate), try_get_timestamp(format.clone(), "01:14".into(), 0.0));
+        assert_eq!(Err(Error::InvalidField('d')), try_get_timestamp(format.clone(), "01:14:05:xx:15:42".into(), 0.0));
+        assert_eq!(Ok(1403322675), try_get_timestamp("%x".into(), "1403322675".into(), 0.0));
+        assert_eq!(Err(Error::NonExistentDate), try_get_timestamp(format.clone(), "01:14:05:45:15:42".into(), 0.0));
+    }
+
+    #[test]
+    fn get_timestamp_test() {
+        let format = String::from("%u:%m:%d:%c:%b");
+        let times = vec![
+            parse_time("1970-01-07T00:06:15+00:00"),
+            parse_time("1970-12-28T00:01:20+00:00"),
+            parse_time("1970-12-28T00:01:30+00:00"),
+            parse_time("2014-01-01T00:03:20+00:00"),
+            parse_time("2016-03-20T08:00:00+00:00"),
+            parse_time("2020-09-15T23:55:01+00:00"),
+            parse_time("2020-09-15T23:55:02+00:00")
+        ];
+        for t in times {
+            let date = get_formatted_date(&format, t, 0.0);
+            let timestamp = get_timestamp(format.clone(), date.clone(), 0.0);
+            assert_eq!(date, get_formatted_date(&format, timestamp, 0.0));
+
+            // First second of the dimiday
+            assert!(timestamp <= t && t - timestamp < 9);
+            assert_ne!(date, get_formatted_date(&format, timestamp - 1, 0.0));
+        }
+    }
+
+    #[test]
+    fn get_timestamp_round_trip_test() {
+        let formats = [
+            "%h:%y:%m:%d:%c:%b", // Lunisolar calendar with gregorian epoch
+            "%h:%y:%s:%d:%c:%b", // Solar calendar with gregorian epoch
+            "%h:%u:%m:%d:%c:%b", // Lunisolar calendar with unix epoch
+            "%h:%u:%s:%d:%c:%b"  // Solar calendar with unix epoch
+        ];
+        let times = [
+            -120000000000, // -1833
+            parse_time("0001-01-01T00:00:00+00:00"),
+            parse_time("1066-10-14T09:00:00+00:00"),
+            parse_time("1623-03-01T12:00:00+00:00"),
+            parse_time("1899-12-31T23:59:59+00:00"),
+            parse_time("1900-01-01T00:00:00+00:00"),
+            parse_time("1950-06-21T06:30:00+00:00"),
+            parse_time("1969-12-22T18:00:00+00:00"),
+            parse_time("1970-03-20T22:10:00+00:00"),
+            parse_time("2014-06-21T03:51:15+00:00"),
+            parse_time("2025-09-22T18:19:00+00:00"),
+            parse_time("2099-12-21T07:00:00+00:00"),
+            parse_time("2999-06-01T00:00:00+00:00")
+        ];
+        let longitudes = [-170.0, -66.0, 0.0, 28.0, 150.0];
+        for format in formats.iter() {
+            for &t in times.iter() {
+                for &lon in longitudes.iter() {
+                    let date = get_formatted_date(format, t, lon);
+                    let timestamp = get_timestamp(format.to_string(), date.clone(), lon);
+                    assert_eq!(date, get_formatted_date(format, timestamp, lon));
+                    assert!(timestamp <= t);
+                }
+            }
+        }
+    }
 
-        assert_eq!(date_year( "00:00:00:00:00:00".into()),     0);
-        assert_eq!(date_year( "00:02:00:00:00:00".into()),     2);
-        assert_eq!(date_year( "00:42:00:00:00:00".into()),    42);
-        assert_eq!(date_year( "03:37:00:00:00:00".into()),   337);
-        assert_eq!(date_year( "13:37:00:00:00:00".into()),  1337);
+    #[test]
+    fn get_timestamp_with_solar_calendar_test() {
+        // Stonehenge coordinates: 51.178844, -1.826189
+        let t = 1403322675;
+        assert_eq!(t - 7, get_timestamp("%u:%s:%d:%c:%b".into(), "44:02:00:15:42".into(), -1.826189));
+        assert_eq!(t - 7, get_timestamp("%h:%y:%s:%d:%c:%b".into(), "01:14:02:00:15:42".into(), -1.826189));
+
+        // There are only 4 seasonal months in a year
+        let res = try_get_timestamp("%u:%s:%d:%c:%b".into(), "44:04:00:00:00".into(), -1.826189);
+        assert_eq!(Err(Error::NonExistentDate), res);
+    }
+
+    #[test]
+    fn get_timestamp_of_non_existent_date_test() {
+        let format = String::from("%u:%m:%d:%c:%b");
 
-        assert_eq!(date_year("-00:00:00:00:00:00".into()),     0);
-        assert_eq!(date_year("-00:02:00:00:00:00".into()),    -2);
-        assert_eq!(date_year("-00:42:00:00:00:00".into()),   -42);
-        assert_eq!(date_year("-03:37:00:00:00:00".into()),  -337);
-        assert_eq!(date_year("-13:37:00:00:00:00".into()), -1337);
+        // The second month of the year 00 has 29 days
+        assert!(try_get_timestamp(format.clone(), "00:01:28:99:99".into(), 0.0).is_ok());
+        assert_eq!(Err(Error::NonExistentDate), try_get_timestamp(format.clone(), "00:01:29:00:00".into(), 0.0));
+
+        // The year 00 has 12 months
+        assert!(try_get_timestamp(format.clone(), "00:11:29:99:99".into(), 0.0).is_ok());
+        assert_eq!(Err(Error::NonExistentDate), try_get_timestamp(format.clone(), "00:12:00:00:00".into(), 0.0));
     }
 
     #[test]
-    fn date_index_test() {
-        assert_eq!(date_index( "00:00:00:00:00:00".into()),             0);
-        assert_eq!(date_index( "00:02:00:00:00:00".into()),     200000000);
-        assert_eq!(date_index("-00:02:00:00:00:00".into()),    -200000000);
-        assert_eq!(date_index("-00:02:05:00:00:00".into()),    -195000000);
-        assert_eq!(date_index("-00:02:10:00:00:00".into()),    -190000000);
-        assert_eq!(date_index("-00:01:00:00:00:00".into()),    -100000000);
-        assert_eq!(date_index("-00:01:10:00:00:00".into()),     -90000000);
-        assert_eq!(date_index("-00:01:11:28:99:99".into()),     -88710001);
+    fn get_timestamp_with_year_start_test() {
+        let formats = ["%h:%y:%m:%d:%c:%b", "%h:%u:%s:%d:%c:%b"];
+        let times = [
+            parse_time("0001-01-01T00:00:00+00:00"),
+            parse_time("1970-03-20T22:10:00+00:00"),
+            parse_time("2014-06-21T03:51:15+00:00"),
+            parse_time("2025-09-22T18:19:00+00:00")
+        ];
+        let year_starts = [YearStart::DecemberSolstice, YearStart::MarchEquinox, YearStart::JuneSolstice];
+        for format in formats.iter() {
+            for &t in times.iter() {
+                for &year_start in year_starts.iter() {
+                    let date = get_formatted_date_with_year_start(format, t, -66.0, year_start);
+                    let timestamp = get_timestamp_with_year_start(format.to_string(), date.clone(), -66.0, year_start);
+                    assert_eq!(date, get_formatted_date_with_year_start(format, timestamp, -66.0, year_start));
+                    assert!(timestamp <= t);
+                }
+            }
+        }
+
+        // The year starting at the june solstice of 2014 is the year 114
+        // of the gregorian epoch, and starts on its day with the solar
+        // calendar or with the following new moon with the lunisolar one
+        let t = parse_time("2014-06-21T12:00:00+00:00");
+        let date = get_formatted_date_with_year_start("%Y:%s:%d", t, 0.0, YearStart::JuneSolstice);
+        assert_eq!("114:00:00", date);
+        let date = get_formatted_date_with_year_start("%Y:%m:%d", t, 0.0, YearStart::JuneSolstice);
+        assert_eq!("113:11:24", date);
+        let date = get_formatted_date_with_year_start("%Y:%m:%d", t, 0.0, YearStart::DecemberSolstice);
+        assert_eq!(get_formatted_date("%Y:%m:%d", t, 0.0), date);
+    }
+
+    #[test]
+    fn get_timestamp_with_custom_epoch_test() {
+        // The year 114 of the gregorian epoch is the year 00 of an epoch
+        // beginning with it, and the year 44 of the unix epoch
+        let t = 1403322675;
+        let epochs = [Epoch::from_year(114), Epoch::Custom(parse_time("2014-01-01T00:00:00+00:00"))];
+        for &epoch in epochs.iter() {
+            let mut context = GeodateContext::new(-1.826189, epoch, Calendar::Lunisolar);
+            let date = get_formatted_date_with_context("%h:%y:%m:%d:%c:%b", t, &mut context);
+            assert_eq!("00:00:05:24:15:42", date);
+            assert_eq!("44:05:24:15:42", get_formatted_date_with_context("%u:%m:%d:%c:%b", t, &mut context));
+
+            let timestamp = get_timestamp_with_format_and_context("%h:%y:%m:%d:%c:%b".into(), date, &mut context);
+            assert_eq!(t - 7, timestamp);
+            let timestamp = get_timestamp_with_format_and_context("%u:%m:%d:%c:%b".into(), "44:05:24:15:42".into(), &mut context);
+            assert_eq!(t - 7, timestamp);
+        }
+
+        // The years before the epoch are negative
+        let mut context = GeodateContext::new(0.0, Epoch::from_year(200), Calendar::Solar);
+        let date = get_formatted_date_with_context("%Y:%m:%d", t, &mut context);
+        assert_eq!("-86:02:00", date);
+        let timestamp = get_timestamp_with_format_and_context("%Y:%m:%d".into(), date.clone(), &mut context);
+        assert_eq!(date, get_formatted_date_with_context("%Y:%m:%d", timestamp, &mut context));
+    }
+
+    #[test]
+    fn get_timestamp_with_custom_epochs_test() {
+        // The years are numbered consecutively with an epoch at any time of
+        // the year, including about half a year from the solstice
+        let mut solstices = vec![get_next_december_solstice(parse_time("2014-12-01T00:00:00+00:00"))];
+        while solstices.len() < 8 {
+            let t = get_next_december_solstice(solstices[solstices.len() - 1]);
+            solstices.push(t);
+        }
+        for i in 0..(366 * 8) {
+            let epoch = Epoch::Custom(solstices[0] - 184 * 86400 + i * 10800);
+            let mut context = GeodateContext::new(0.0, epoch, Calendar::Solar);
+            let dates: Vec<Geodate> = solstices.iter().map(|&t| Geodate::new_with_context(t, &mut context)).collect();
+            for (j, date) in dates.iter().enumerate() {
+                assert_eq!((dates[0].signed_year() + j as i64, 0, 0), (date.signed_year(), date.month, date.day));
+                let timestamp = get_timestamp_with_context(date, &mut context);
+                assert_eq!(*date, Geodate::new_with_context(timestamp, &mut context));
+            }
+        }
+    }
+
+    #[test]
+    fn get_timestamp_with_mean_solar_time_test() {
+        let context = GeodateContext::new(-1.826189, Epoch::Gregorian, Calendar::Lunisolar);
+        let mut context = context.with_solar_time(SolarTime::Mean);
+        let format = "%h:%y:%m:%d:%c:%b";
+        let times = [
+            parse_time("1970-12-28T00:03:00+00:00"),
+            parse_time("2014-06-21T03:51:15+00:00"),
+            parse_time("2020-09-15T23:55:01+00:00")
+        ];
+        for &t in times.iter() {
+            let date = get_formatted_date_with_context(format, t, &mut context);
+            let timestamp = get_timestamp_with_format_and_context(format.into(), date.clone(), &mut context);
+            assert_eq!(date, get_formatted_date_with_context(format, timestamp, &mut context));
+            assert!(t - 9 < timestamp && timestamp <= t);
+        }
+
+        // The midnight is 7 minutes and 18 seconds after 00:00 UTC
+        let timestamp = get_timestamp_with_format_and_context(format.into(), "01:14:05:24:00:00".into(), &mut context);
+        assert_eq!(parse_time("2014-06-21T00:07:18+00:00"), timestamp);
+    }
+
+    #[test]
+    fn try_get_timestamp_with_context_test() {
+        let mut context = GeodateContext::new(-1.826189, Epoch::Gregorian, Calendar::Lunisolar);
+        let dates = vec!["01:14:05:24:15:42", "01:14:05:24:15:43", "01:14:05:25:00:00"];
+        for date in dates {
+            let timestamp = try_get_timestamp_with_context(&date.parse().unwrap(), &mut context).unwrap();
+            assert_eq!(date, Geodate::new_with_context(timestamp, &mut context).to_string());
+        }
+
+        // The configuration of the date is used instead of the context
+        let t = parse_time("2014-06-21T03:51:15+00:00");
+        let mut other_context = GeodateContext::new(-1.826189, Epoch::Unix, Calendar::Solar).with_solar_time(SolarTime::Mean);
+        let date = Geodate::new_with_context(t, &mut other_context);
+        let timestamp = try_get_timestamp_with_context(&date, &mut other_context);
+        assert_eq!(timestamp, try_get_timestamp_with_context(&date, &mut context));
+        assert_eq!(date, Geodate::new_with_context(timestamp.unwrap(), &mut other_context));
     }
 }
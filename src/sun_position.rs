@@ -0,0 +1,165 @@
+use coordinates::*;
+use delta_time::*;
+use error::Error;
+use julian::*;
+use math::*;
+use observer::Observer;
+use sun_transit::mean_obliquity_eliptic;
+use timestamp::Timestamp;
+
+#[cfg(not(feature = "std"))]
+use num_traits::Float;
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+/// The position of the Sun in the sky of an observer
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct SunPosition {
+    /// Apparent right ascension in degrees
+    pub right_ascension: f64,
+    /// Apparent declination in degrees
+    pub declination: f64,
+    /// Azimuth in degrees measured clockwise from the north
+    pub azimuth: f64,
+    /// Altitude of the center of the Sun in degrees above the horizon,
+    /// without the atmospheric refraction
+    pub altitude: f64,
+    /// Distance from the Earth in astronomical units
+    pub distance: f64
+}
+
+impl SunPosition {
+    /// Get the altitude of the center of the Sun raised by the atmospheric
+    /// refraction, as seen in the sky
+    pub fn apparent_altitude(&self) -> f64 {
+        self.altitude + get_refraction(self.altitude)
+    }
+}
+
+/// Get the position of the Sun seen by an observer
+pub fn get_sun_position<T: Timestamp>(observer: &Observer, timestamp: T) -> SunPosition {
+    try_get_sun_position(observer, timestamp).unwrap()
+}
+
+/// Get the position of the Sun seen by an observer
+///
+/// The position is accurate to about 0.01° for the years -1999 to +3000.
+pub fn try_get_sun_position<T: Timestamp>(observer: &Observer, timestamp: T) -> Result<SunPosition, Error> {
+    let timestamp = timestamp.unix_timestamp();
+
+    // Julian day and julian ephemeris day
+    let jd = unix_to_julian(timestamp);
+    let jde = jd + try_delta_time(unix_to_year(timestamp))? / 86400.0;
+
+    let (a, d, r) = get_sun_equatorial_coordinates(jde);
+    let (azimuth, altitude) = get_horizontal_coordinates(observer, get_sidereal_time(jd), a, d);
+
+    Ok(SunPosition {
+        right_ascension: a,
+        declination: d,
+        azimuth,
+        altitude,
+        distance: r
+    })
+}
+
+// Get the apparent right ascension and declination of the Sun in degrees,
+// and its distance in astronomical units, at a julian ephemeris day
+pub(crate) fn get_sun_equatorial_coordinates(julian_day: f64) -> (f64, f64, f64) {
+    // Julian century
+    let t = jde_to_julian_century(julian_day);
+
+    // Geometric mean longitude of the Sun
+    // (formula 25.2)
+    // (L0)
+    let l0 = 280.466_46 + 36_000.769_83 * t + 0.000_303_2 * t.powi(2);
+
+    // Mean anomaly of the Sun
+    // (formula 25.3)
+    // (M)
+    let m = 357.529_11 + 35_999.050_29 * t - 0.000_153_7 * t.powi(2);
+
+    // Eccentricity of the Earth orbit
+    // (formula 25.4)
+    // (e)
+    let e = 0.016_708_634 - 0.000_042_037 * t - 0.000_000_126_7 * t.powi(2);
+
+    // Equation of the center
+    // (C)
+    let c = sin_deg(1.0 * m) * (1.914_602 - 0.004_817 * t - 0.000_014 * t.powi(2))
+          + sin_deg(2.0 * m) * (0.019_993 - 0.000_101 * t)
+          + sin_deg(3.0 * m) * (0.000_289);
+
+    // True longitude and true anomaly
+    // (☉, v)
+    let o = l0 + c;
+    let v = m + c;
+
+    // Radius vector
+    // (formula 25.5)
+    // (R)
+    let r = 1.000_001_018 * (1.0 - e.powi(2)) / (1.0 + e * cos_deg(v));
+
+    // Apparent longitude
+    // (λ)
+    let p = 125.04 - 1934.136 * t;
+    let l = o - 0.005_69 - 0.004_78 * sin_deg(p);
+
+    // Apparent obliquity of the ecliptic
+    // (formula 25.8)
+    // (ε)
+    let ep = mean_obliquity_eliptic(t) + 0.002_56 * cos_deg(p);
+
+    // Apparent right ascension and declination
+    // (formula 25.6 and 25.7)
+    // (α, δ)
+    let a = modulo(atan2_deg(cos_deg(ep) * sin_deg(l), cos_deg(l)), 360.0);
+    let d = asin_deg(sin_deg(ep) * sin_deg(l));
+
+    (a, d, r)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use sun_transit::*;
+    use utils::*;
+
+    #[test]
+    fn get_sun_position_test() {
+        // Example 25.a from "Astronomical Algoritms"
+        let observer = Observer::new(0.0, 0.0, 0.0);
+        let t = parse_time("1992-10-13T00:00:00+00:00") - 59;
+        let position = get_sun_position(&observer, t);
+        assert_approx_eq!(dec_deg(198.0, 22.0, 50.99), position.right_ascension, 0.001);
+        assert_approx_eq!(-dec_deg(7.0, 47.0, 6.25), position.declination, 0.001);
+        assert_approx_eq!(0.99766, position.distance, 0.000_01);
+
+        // The Earth is nearest to the Sun in january and farthest in july
+        assert_approx_eq!(0.9833, get_sun_position(&observer, parse_time("2014-01-04T12:00:00+00:00")).distance, 0.000_1);
+        assert_approx_eq!(1.0167, get_sun_position(&observer, parse_time("2014-07-04T00:00:00+00:00")).distance, 0.000_1);
+
+        assert_eq!(Err(Error::OutOfRange), try_get_sun_position(&observer, parse_time("3100-01-01T00:00:00+00:00")));
+    }
+
+    #[test]
+    fn get_sun_position_in_the_sky_test() {
+        let observer = Observer::new(51.178844, -1.826189, 0.0);
+        let t = parse_time("2014-06-21T12:00:00+00:00");
+
+        // The Sun culminates due south at noon
+        let position = get_sun_position(&observer, get_noon(t, -1.826189));
+        assert_approx_eq!(180.0, position.azimuth, 0.1);
+        assert_approx_eq!(90.0 - 51.178844 + 23.44, position.altitude, 0.01);
+        assert!(position.apparent_altitude() > position.altitude);
+
+        // The center of the Sun is 0.83° below the horizon at sunrise, but
+        // is seen higher because of the refraction
+        let sunrise = get_sunrise(t, -1.826189, 51.178844).unwrap();
+        let position = get_sun_position(&observer, sunrise);
+        assert_approx_eq!(-0.83, position.altitude, 0.05);
+        assert_approx_eq!(-0.21, position.apparent_altitude(), 0.05);
+        assert!(0.0 < position.azimuth && position.azimuth < 90.0);
+    }
+}
@@ -1,8 +1,40 @@
 use julian::*;
 use math::*;
+use observer::*;
+use timestamp::Timestamp;
 
 #[cfg(not(feature = "std"))]
 use num_traits::Float;
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+// Altitude of the center of the Sun at sunrise and sunset, below the
+// horizon because of the refraction and the semidiameter of the Sun
+const SUNRISE_ALTITUDE: f64 = -0.83;
+
+/// A twilight given by the altitude of the Sun below the horizon
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum Twilight {
+    /// The Sun is less than 6° below the horizon
+    Civil,
+    /// The Sun is less than 12° below the horizon
+    Nautical,
+    /// The Sun is less than 18° below the horizon
+    Astronomical
+}
+
+impl Twilight {
+    /// Get the altitude of the Sun in degrees at the beginning of the dawn
+    /// and at the end of the dusk
+    pub fn altitude(&self) -> f64 {
+        match self {
+            Twilight::Civil        => -6.0,
+            Twilight::Nautical     => -12.0,
+            Twilight::Astronomical => -18.0
+        }
+    }
+}
 
 #[derive(PartialEq)]
 enum Event {
@@ -12,6 +44,7 @@ enum Event {
     Sunset
 }
 
+#[allow(clippy::inconsistent_digit_grouping)]
 fn get_time_of(event: Event, timestamp: i64, longitude: f64, latitude: f64, altitude: f64) -> Option<i64> {
     // Julian day
     let jd = (unix_to_julian(timestamp) + longitude / 360.0 + 0.5).floor();
@@ -104,8 +137,7 @@ fn get_time_of(event: Event, timestamp: i64, longitude: f64, latitude: f64, alti
     let d = asin_deg(sin_deg(ecliptic_longitude) * sin_deg(23.44));
 
     // Hour Angle
-    let alt = -2.076 * altitude.sqrt() / 60.0;
-    let w = acos_deg((sin_deg(alt - 0.83) - sin_deg(latitude) * sin_deg(d)) /
+    let w = acos_deg((sin_deg(altitude) - sin_deg(latitude) * sin_deg(d)) /
                      (cos_deg(latitude) * cos_deg(d)));
 
     if (event == Event::Sunrise || event == Event::Sunset) && w.is_nan() {
@@ -122,6 +154,7 @@ fn get_time_of(event: Event, timestamp: i64, longitude: f64, latitude: f64, alti
     Some(julian_to_unix(jd_event))
 }
 
+#[allow(clippy::inconsistent_digit_grouping)]
 pub fn nutation(julian_century: f64) -> (f64, f64) {
     // TODO: The accuracy of this calculation can be improved
 
@@ -217,24 +250,55 @@ pub fn mean_obliquity_eliptic(julian_century: f64) -> f64 {
         + dec_deg(0.0, 0.0, 0.001_813) * t.powi(3)
 }
 
-pub fn get_noon(timestamp: i64, longitude: f64) -> i64 {
+pub fn get_noon<T: Timestamp>(timestamp: T, longitude: f64) -> i64 {
     get_midday(timestamp, longitude)
 }
 
-pub fn get_midday(timestamp: i64, longitude: f64) -> i64 {
-    get_time_of(Event::Midday, timestamp, longitude, 0.0, 0.0).unwrap()
+pub fn get_midday<T: Timestamp>(timestamp: T, longitude: f64) -> i64 {
+    get_time_of(Event::Midday, timestamp.unix_timestamp(), longitude, 0.0, 0.0).unwrap()
+}
+
+pub fn get_midnight<T: Timestamp>(timestamp: T, longitude: f64) -> i64 {
+    get_time_of(Event::Midnight, timestamp.unix_timestamp(), longitude, 0.0, 0.0).unwrap()
+}
+
+pub fn get_sunrise<T: Timestamp>(timestamp: T, longitude: f64, latitude: f64) -> Option<i64> {
+    get_time_of(Event::Sunrise, timestamp.unix_timestamp(), longitude, latitude, SUNRISE_ALTITUDE)
+}
+
+pub fn get_sunset<T: Timestamp>(timestamp: T, longitude: f64, latitude: f64) -> Option<i64> {
+    get_time_of(Event::Sunset, timestamp.unix_timestamp(), longitude, latitude, SUNRISE_ALTITUDE)
+}
+
+/// Get the sunrise seen by an observer above the dip of its horizon
+pub fn get_sunrise_with_observer<T: Timestamp>(timestamp: T, observer: &Observer) -> Option<i64> {
+    get_time_of_sun_altitude(timestamp, observer, SUNRISE_ALTITUDE - observer.horizon_dip(), true)
 }
 
-pub fn get_midnight(timestamp: i64, longitude: f64) -> i64 {
-    get_time_of(Event::Midnight, timestamp, longitude, 0.0, 0.0).unwrap()
+/// Get the sunset seen by an observer above the dip of its horizon
+pub fn get_sunset_with_observer<T: Timestamp>(timestamp: T, observer: &Observer) -> Option<i64> {
+    get_time_of_sun_altitude(timestamp, observer, SUNRISE_ALTITUDE - observer.horizon_dip(), false)
 }
 
-pub fn get_sunrise(timestamp: i64, longitude: f64, latitude: f64) -> Option<i64> {
-    get_time_of(Event::Sunrise, timestamp, longitude, latitude, 0.0)
+/// Get the beginning of the morning twilight of a day
+pub fn get_dawn<T: Timestamp>(timestamp: T, observer: &Observer, twilight: Twilight) -> Option<i64> {
+    get_time_of_sun_altitude(timestamp, observer, twilight.altitude(), true)
 }
 
-pub fn get_sunset(timestamp: i64, longitude: f64, latitude: f64) -> Option<i64> {
-    get_time_of(Event::Sunset, timestamp, longitude, latitude, 0.0)
+/// Get the end of the evening twilight of a day
+pub fn get_dusk<T: Timestamp>(timestamp: T, observer: &Observer, twilight: Twilight) -> Option<i64> {
+    get_time_of_sun_altitude(timestamp, observer, twilight.altitude(), false)
+}
+
+/// Get the time of a day when the center of the Sun is rising or setting
+/// through an altitude in degrees
+///
+/// There is no such time if the Sun stays above or below the altitude for
+/// the whole day.
+pub fn get_time_of_sun_altitude<T: Timestamp>(timestamp: T, observer: &Observer, altitude: f64, rising: bool) -> Option<i64> {
+    let event = if rising { Event::Sunrise } else { Event::Sunset };
+    let (lon, lat) = (observer.longitude(), observer.latitude());
+    get_time_of(event, timestamp.unix_timestamp(), lon, lat, altitude)
 }
 
 #[cfg(test)]
@@ -243,6 +307,7 @@ mod tests {
     use utils::*;
 
     #[test]
+    #[allow(clippy::inconsistent_digit_grouping)]
     fn nutation_test() {
         // Example 22.a from "Astronomical Algoritms"
         // FIXME: Should be JDE instead of JD which differ by ΔT
@@ -268,6 +333,7 @@ mod tests {
     }
 
     #[test]
+    #[allow(clippy::inconsistent_digit_grouping)]
     fn mean_obliquity_eliptic_test() {
         // Example 22.a from "Astronomical Algoritms"
         // FIXME: Should be JDE instead of JD which differ by ΔT
@@ -365,4 +431,66 @@ mod tests {
             assert_approx_eq!(parse_time(t0), get_sunset(parse_time(t1), lon, lat).unwrap(), accuracy);
         }
     }
+
+    #[test]
+    fn get_sunrise_with_observer_test() {
+        let t = parse_time("2010-09-23T12:00:00+00:00");
+        let observer = Observer::new(45.0, 0.0, 0.0);
+        assert_eq!(get_sunrise(t, 0.0, 45.0), get_sunrise_with_observer(t, &observer));
+        assert_eq!(get_sunset(t, 0.0, 45.0), get_sunset_with_observer(t, &observer));
+
+        // The day is about 12 minutes longer at 1000 m above sea level
+        let observer = Observer::new(45.0, 0.0, 1000.0);
+        let sunrise = get_sunrise_with_observer(t, &observer).unwrap();
+        let sunset = get_sunset_with_observer(t, &observer).unwrap();
+        assert_approx_eq!(get_sunrise(t, 0.0, 45.0).unwrap() - 370, sunrise, 30);
+        assert_approx_eq!(get_sunset(t, 0.0, 45.0).unwrap() + 370, sunset, 30);
+
+        // The sun can rise above a horizon lowered by the elevation
+        let t = parse_time("2010-12-21T12:00:00+00:00");
+        assert_eq!(None, get_sunrise(t, 0.0, 67.5));
+        assert!(get_sunrise_with_observer(t, &Observer::new(67.5, 0.0, 1000.0)).is_some());
+    }
+
+    #[test]
+    fn get_dawn_and_dusk_test() {
+        let t = parse_time("2010-09-23T12:00:00+00:00");
+        let observer = Observer::new(45.0, 0.0, 0.0);
+        let sunrise = get_sunrise(t, 0.0, 45.0).unwrap();
+        let sunset = get_sunset(t, 0.0, 45.0).unwrap();
+
+        // The twilights last about 30 minutes per 6° at the equinox
+        let twilights = [Twilight::Civil, Twilight::Nautical, Twilight::Astronomical];
+        for (i, &twilight) in twilights.iter().enumerate() {
+            let dawn = get_dawn(t, &observer, twilight).unwrap();
+            let dusk = get_dusk(t, &observer, twilight).unwrap();
+            let duration = 30 * 60 * (i as i64 + 1) + 4 * 60;
+            assert_approx_eq!(sunrise - duration, dawn, 5 * 60);
+            assert_approx_eq!(sunset + duration, dusk, 5 * 60);
+        }
+
+        // The sky never gets fully dark around the june solstice at
+        // Stonehenge
+        let t = parse_time("2014-06-21T12:00:00+00:00");
+        let observer = Observer::new(51.178844, -1.826189, 0.0);
+        assert!(get_dawn(t, &observer, Twilight::Nautical).is_some());
+        assert_eq!(None, get_dawn(t, &observer, Twilight::Astronomical));
+        assert_eq!(None, get_dusk(t, &observer, Twilight::Astronomical));
+    }
+
+    #[test]
+    fn get_time_of_sun_altitude_test() {
+        let t = parse_time("2010-06-21T12:00:00+00:00");
+        let observer = Observer::new(45.0, 0.0, 0.0);
+        assert_eq!(get_sunrise(t, 0.0, 45.0), get_time_of_sun_altitude(t, &observer, -0.83, true));
+        assert_eq!(get_sunset(t, 0.0, 45.0), get_time_of_sun_altitude(t, &observer, -0.83, false));
+        assert_eq!(get_dawn(t, &observer, Twilight::Civil), get_time_of_sun_altitude(t, &observer, -6.0, true));
+
+        // The Sun is at most 68.4° high at the june solstice
+        let noon = get_noon(t, 0.0);
+        let morning = get_time_of_sun_altitude(t, &observer, 60.0, true).unwrap();
+        let afternoon = get_time_of_sun_altitude(t, &observer, 60.0, false).unwrap();
+        assert!(morning < noon && noon < afternoon);
+        assert_eq!(None, get_time_of_sun_altitude(t, &observer, 70.0, true));
+    }
 }
@@ -0,0 +1,162 @@
+#[cfg(any(feature = "time", feature = "chrono"))]
+use geodate::*;
+#[cfg(any(feature = "time", feature = "chrono"))]
+use calendar::get_day_start;
+#[cfg(any(feature = "time", feature = "chrono"))]
+use error::Error;
+#[cfg(any(feature = "time", feature = "chrono"))]
+use reverse::try_get_timestamp_with_context;
+
+#[cfg(any(feature = "time", feature = "chrono"))]
+use core::convert::TryFrom;
+
+/// A point in time accepted by the functions of the crate
+///
+/// The fraction of a second of a time is dropped by rounding it down.
+pub trait Timestamp {
+    /// Get the number of seconds since the unix epoch
+    fn unix_timestamp(&self) -> i64;
+}
+
+impl Timestamp for i64 {
+    fn unix_timestamp(&self) -> i64 {
+        *self
+    }
+}
+
+#[cfg(feature = "time")]
+impl Timestamp for time::OffsetDateTime {
+    fn unix_timestamp(&self) -> i64 {
+        time::OffsetDateTime::unix_timestamp(*self)
+    }
+}
+
+#[cfg(feature = "chrono")]
+impl<Tz: chrono::TimeZone> Timestamp for chrono::DateTime<Tz> {
+    fn unix_timestamp(&self) -> i64 {
+        self.timestamp()
+    }
+}
+
+// Get the exact beginning of the dimiday of a geodate in seconds and
+// nanoseconds since the unix epoch
+#[cfg(any(feature = "time", feature = "chrono"))]
+fn try_get_exact_timestamp(date: &Geodate, longitude: f64) -> Result<(i64, u32), Error> {
+    let mut context = date.get_context(longitude);
+    let timestamp = try_get_timestamp_with_context(date, &mut context)?;
+    let midnight = get_day_start(timestamp, longitude, date.solar_time);
+
+    // A dimiday lasts 8.64 seconds
+    let nanoseconds = (date.centiday * 100 + date.dimiday) * 8_640_000_000;
+
+    Ok((midnight + nanoseconds / 1_000_000_000, (nanoseconds % 1_000_000_000) as u32))
+}
+
+// Get the lunisolar geodate with the gregorian epoch of a time given in
+// seconds and nanoseconds since the unix epoch, keeping the fraction of a
+// second in the dimidays
+#[cfg(any(feature = "time", feature = "chrono"))]
+fn try_get_exact_geodate(seconds: i64, nanoseconds: u32, longitude: f64) -> Result<Geodate, Error> {
+    let date = Geodate::try_new(seconds, longitude)?;
+    let midnight = get_day_start(seconds, longitude, date.solar_time);
+
+    let nanoseconds = (seconds - midnight) as i128 * 1_000_000_000 + nanoseconds as i128;
+    let e = (10000 * nanoseconds / 86_400_000_000_000) as i64;
+
+    Ok(Geodate { centiday: e / 100, dimiday: e % 100, ..date })
+}
+
+/// Get the lunisolar geodate with the gregorian epoch of a time at a longitude
+#[cfg(feature = "time")]
+impl TryFrom<(time::OffsetDateTime, f64)> for Geodate {
+    type Error = Error;
+
+    fn try_from((datetime, longitude): (time::OffsetDateTime, f64)) -> Result<Geodate, Error> {
+        try_get_exact_geodate(datetime.unix_timestamp(), datetime.nanosecond(), longitude)
+    }
+}
+
+/// Get the lunisolar geodate with the gregorian epoch of a time at a longitude
+#[cfg(feature = "chrono")]
+impl<Tz: chrono::TimeZone> TryFrom<(chrono::DateTime<Tz>, f64)> for Geodate {
+    type Error = Error;
+
+    fn try_from((datetime, longitude): (chrono::DateTime<Tz>, f64)) -> Result<Geodate, Error> {
+        try_get_exact_geodate(datetime.timestamp(), datetime.timestamp_subsec_nanos(), longitude)
+    }
+}
+
+#[cfg(feature = "time")]
+impl Geodate {
+    /// Get the beginning of the dimiday of a geodate at a longitude
+    pub fn try_to_offset_date_time(&self, longitude: f64) -> Result<time::OffsetDateTime, Error> {
+        let (seconds, nanoseconds) = try_get_exact_timestamp(self, longitude)?;
+        let nanoseconds = seconds as i128 * 1_000_000_000 + nanoseconds as i128;
+        time::OffsetDateTime::from_unix_timestamp_nanos(nanoseconds).map_err(|_| Error::OutOfRange)
+    }
+}
+
+#[cfg(feature = "chrono")]
+impl Geodate {
+    /// Get the beginning of the dimiday of a geodate at a longitude
+    pub fn try_to_date_time(&self, longitude: f64) -> Result<chrono::DateTime<chrono::Utc>, Error> {
+        let (seconds, nanoseconds) = try_get_exact_timestamp(self, longitude)?;
+        chrono::DateTime::from_timestamp(seconds, nanoseconds).ok_or(Error::OutOfRange)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use sun_transit::*;
+    #[cfg(any(feature = "time", feature = "chrono"))]
+    use alloc::string::ToString;
+
+    #[test]
+    fn timestamp_test() {
+        assert_eq!(1403322675, 1403322675.unix_timestamp());
+        assert_eq!(Some(1403322705), get_sunrise(1403322675, -1.826189, 51.178844));
+    }
+
+    #[cfg(feature = "time")]
+    #[test]
+    fn time_test() {
+        let datetime = time::OffsetDateTime::from_unix_timestamp_nanos(1_403_322_675_500_000_000).unwrap();
+        assert_eq!(1403322675, Timestamp::unix_timestamp(&datetime));
+        assert_eq!(Some(1403322705), get_sunrise(datetime, -1.826189, 51.178844));
+
+        let date = Geodate::try_from((datetime, -1.826189)).unwrap();
+        assert_eq!("01:14:05:24:15:42", date.to_string());
+
+        // The dimiday began at 1403322667.88
+        let datetime = date.try_to_offset_date_time(-1.826189).unwrap();
+        assert_eq!(1403322667, datetime.unix_timestamp());
+        assert_eq!(880_000_000, datetime.nanosecond());
+
+        // The next dimiday begins at 1403322676.52
+        let datetime = time::OffsetDateTime::from_unix_timestamp_nanos(1_403_322_676_600_000_000).unwrap();
+        assert_eq!("01:14:05:24:15:42", Geodate::new(datetime, -1.826189).to_string());
+        assert_eq!("01:14:05:24:15:43", Geodate::try_from((datetime, -1.826189)).unwrap().to_string());
+    }
+
+    #[cfg(feature = "chrono")]
+    #[test]
+    fn chrono_test() {
+        let datetime = chrono::DateTime::from_timestamp(1403322675, 500_000_000).unwrap();
+        assert_eq!(1403322675, Timestamp::unix_timestamp(&datetime));
+        assert_eq!(Some(1403322705), get_sunrise(datetime, -1.826189, 51.178844));
+
+        let date = Geodate::try_from((datetime, -1.826189)).unwrap();
+        assert_eq!("01:14:05:24:15:42", date.to_string());
+
+        // The dimiday began at 1403322667.88
+        let datetime = date.try_to_date_time(-1.826189).unwrap();
+        assert_eq!(1403322667, datetime.timestamp());
+        assert_eq!(880_000_000, datetime.timestamp_subsec_nanos());
+
+        // The next dimiday begins at 1403322676.52
+        let datetime = chrono::DateTime::from_timestamp(1403322676, 600_000_000).unwrap();
+        assert_eq!("01:14:05:24:15:42", Geodate::new(datetime, -1.826189).to_string());
+        assert_eq!("01:14:05:24:15:43", Geodate::try_from((datetime, -1.826189)).unwrap().to_string());
+    }
+}